@@ -1,6 +1,9 @@
+use crate::data::signing_key::{SigningKey, SigningKeyUpdate, SigningKeys};
 use crate::errors;
 use async_std;
 use serde_json;
+use std::thread::JoinHandle;
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub struct AccessControlApi {
@@ -8,12 +11,41 @@ pub struct AccessControlApi {
     pub urls: crate::api::urls::LivepeerUrls,
 }
 
+/// A rotated signing key, with the old key retired in the background once its grace period is over
+///
+/// Dropping it leaves the retirement running.
+#[derive(Debug)]
+pub struct SigningKeyRotation {
+    pub new_key: SigningKey,
+    pub old_key_id: String,
+    retire: Option<JoinHandle<Result<(), errors::Error>>>,
+}
+
 impl crate::accesscontrol::AccessControl for AccessControlApi {
-    fn list_signing_keys(&self) -> Result<serde_json::Value, errors::Error> {
-        self._get_signing_keys()
+    fn list_signing_keys(&self) -> Result<SigningKeys, errors::Error> {
+        let keys = self._get_signing_keys()?;
+        serde_json::from_value(keys).map_err(|_| errors::Error::SIGNINGKEY)
     }
-    fn create_signing_key(&self, name: String) -> Result<serde_json::Value, errors::Error> {
-        self._create_signing_key(name)
+    fn create_signing_key(&self, name: String) -> Result<SigningKey, errors::Error> {
+        let key = self._create_signing_key(name)?;
+        serde_json::from_value(key).map_err(|_| errors::Error::SIGNINGKEY)
+    }
+    fn get_signing_key(&self, id: String) -> Result<SigningKey, errors::Error> {
+        let key = self._get_signing_key(id)?;
+        serde_json::from_value(key).map_err(|_| errors::Error::SIGNINGKEY)
+    }
+    fn update_signing_key(
+        &self,
+        id: String,
+        name: Option<String>,
+        disabled: Option<bool>,
+    ) -> Result<(), errors::Error> {
+        let payload = serde_json::to_value(SigningKeyUpdate { name, disabled })
+            .map_err(|_| errors::Error::SIGNINGKEY)?;
+        self._update_signing_key(id, payload).map(|_| ())
+    }
+    fn disable_signing_key(&self, id: String) -> Result<(), errors::Error> {
+        self.update_signing_key(id, None, Some(true))
     }
     fn delete_signing_key(&self, id: String) -> Result<serde_json::Value, errors::Error> {
         self._delete_signing_key(id)
@@ -40,6 +72,16 @@ impl AccessControlApi {
         )
     }
 
+    pub fn _get_signing_key(&self, id: String) -> Result<serde_json::Value, errors::Error> {
+        crate::utils::SurfRequest::get(
+            format!(
+                "{}{}/{}",
+                self.client.config.host, self.urls.access_control.signing_key, id
+            ),
+            self.client.clone(),
+        )
+    }
+
     pub fn _create_signing_key(
         &self,
         name: String,
@@ -54,6 +96,21 @@ impl AccessControlApi {
         )
     }
 
+    pub fn _update_signing_key(
+        &self,
+        id: String,
+        payload: serde_json::Value,
+    ) -> Result<serde_json::Value, errors::Error> {
+        crate::utils::SurfRequest::patch(
+            format!(
+                "{}{}/{}",
+                self.client.config.host, self.urls.access_control.signing_key, id
+            ),
+            payload.to_string(),
+            self.client.clone(),
+        )
+    }
+
     pub fn _delete_signing_key(&self, id: String) -> Result<serde_json::Value, errors::Error> {
         crate::utils::SurfRequest::delete(
             format!(
@@ -63,4 +120,94 @@ impl AccessControlApi {
             self.client.clone(),
         )
     }
+
+    /// Rotate a signing key
+    ///
+    /// Creates a new key and hands it to `switch_over` so callers can start signing with it.
+    /// The old key stays enabled for `grace`, so tokens it signed keep working until they
+    /// expire, then a background thread retires it with `retire_signing_key`. That thread dies
+    /// with the process, so if it may exit before `grace` is over, keep the old key's ID and
+    /// call `retire_signing_key` later, e.g. from a scheduled job.
+    ///
+    /// # Parameters
+    /// * `old_id` - The ID of the signing key being replaced
+    /// * `name` - The name of the new signing key
+    /// * `grace` - How long the old key keeps working after the switch over
+    /// * `delete` - Delete the old key once disabled, rather than keep it disabled
+    /// * `switch_over` - Called with the new key, including its private key; an error aborts the rotation and leaves the old key alone
+    ///
+    /// # Returns
+    /// * `Result<SigningKeyRotation, errors::Error>` - The new signing key and the pending retirement of the old one, or an error
+    pub fn rotate_signing_key<F>(
+        &self,
+        old_id: String,
+        name: String,
+        grace: Duration,
+        delete: bool,
+        switch_over: F,
+    ) -> Result<SigningKeyRotation, errors::Error>
+    where
+        F: FnOnce(&SigningKey) -> Result<(), errors::Error>,
+    {
+        use crate::accesscontrol::AccessControl;
+
+        let new_key = self.create_signing_key(name)?;
+        if let Err(e) = switch_over(&new_key) {
+            // Nothing signs with the new key yet, don't leave it behind
+            let _ = self.delete_signing_key(new_key.id.clone());
+            return Err(e);
+        }
+
+        let api = self.clone();
+        let id = old_id.clone();
+        let retire = std::thread::spawn(move || {
+            std::thread::sleep(grace);
+            api.retire_signing_key(id, delete)
+        });
+        Ok(SigningKeyRotation {
+            new_key,
+            old_key_id: old_id,
+            retire: Some(retire),
+        })
+    }
+
+    /// Retire a signing key replaced by `rotate_signing_key`
+    ///
+    /// The key is disabled before it is deleted, so tokens it signed are rejected even if the
+    /// delete fails and has to be retried.
+    ///
+    /// # Parameters
+    /// * `id` - The ID of the signing key to retire
+    /// * `delete` - Delete the key once disabled
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result or an error
+    pub fn retire_signing_key(&self, id: String, delete: bool) -> Result<(), errors::Error> {
+        use crate::accesscontrol::AccessControl;
+
+        self.disable_signing_key(id.clone())?;
+        if delete {
+            self.delete_signing_key(id)?;
+        }
+        Ok(())
+    }
+}
+
+impl SigningKeyRotation {
+    /// Whether the old key's grace period is over and retiring it has finished, or failed
+    pub fn is_retired(&self) -> bool {
+        self.retire.as_ref().map_or(true, |retire| retire.is_finished())
+    }
+
+    /// Block until the old key is retired
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result, or the error that stopped the old key being disabled or deleted
+    pub fn wait(mut self) -> Result<(), errors::Error> {
+        match self.retire.take().map(|retire| retire.join()) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(errors::Error::SIGNINGKEY),
+            None => Ok(()),
+        }
+    }
 }
@@ -0,0 +1,247 @@
+use crate::errors;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use p256::ecdsa::signature::{Signer, Verifier};
+use p256::ecdsa::{Signature, SigningKey, VerifyingKey};
+use p256::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePublicKey, LineEnding};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_ISSUER: &str = "Livepeer Studio";
+pub const PLAYBACK_CDN: &str = "https://livepeercdn.studio";
+
+/// Claims carried by a Livepeer playback JWT
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlaybackClaims {
+    /// Playback ID the token grants access to
+    pub sub: String,
+    pub action: String,
+    pub iss: String,
+    /// Base64 PEM public key of the signing key, used by Studio to look it up
+    #[serde(rename = "pub")]
+    pub public_key: String,
+    pub video: String,
+    /// Expiry as a unix timestamp in seconds
+    pub exp: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom: Option<Value>,
+}
+
+/// Signs playback JWTs locally with a Studio signing key
+#[derive(Debug, Clone)]
+pub struct PlaybackTokenSigner {
+    signing_key: SigningKey,
+    public_key: String,
+    pub issuer: String,
+}
+
+impl PlaybackTokenSigner {
+    /// Create a signer from a signing key's private key
+    ///
+    /// The public key is derived from the private key.
+    ///
+    /// # Parameters
+    /// * `private_key` - The private key, as base64 PEM returned by Studio or as plain PEM
+    ///
+    /// # Returns
+    /// * `Result<Self, errors::Error>` - A new signer or an error
+    pub fn new(private_key: &String) -> Result<Self, errors::Error> {
+        let pem = decode_pem(private_key)?;
+        let signing_key = SigningKey::from_pkcs8_pem(&pem)
+            .or_else(|_| p256::SecretKey::from_sec1_pem(&pem).map(SigningKey::from))
+            .map_err(|_| errors::Error::INVALIDSIGNINGKEY)?;
+        let public_pem = signing_key
+            .verifying_key()
+            .to_public_key_pem(LineEnding::LF)
+            .map_err(|_| errors::Error::INVALIDSIGNINGKEY)?;
+
+        Ok(PlaybackTokenSigner {
+            signing_key,
+            public_key: STANDARD.encode(public_pem),
+            issuer: DEFAULT_ISSUER.to_string(),
+        })
+    }
+
+    /// Create a signer from a signing key object returned by `create_signing_key`
+    ///
+    /// # Parameters
+    /// * `signing_key` - The signing key, which must still carry its private key
+    ///
+    /// # Returns
+    /// * `Result<Self, errors::Error>` - A new signer or an error
+    pub fn from_signing_key(
+        signing_key: &crate::data::signing_key::SigningKey,
+    ) -> Result<Self, errors::Error> {
+        let private_key = signing_key
+            .private_key
+            .as_ref()
+            .ok_or(errors::Error::INVALIDSIGNINGKEY)?;
+        let mut signer = Self::new(private_key)?;
+        signer.public_key = signing_key.public_key.clone();
+        Ok(signer)
+    }
+
+    /// Base64 PEM public key embedded in the `pub` claim
+    pub fn public_key(&self) -> &String {
+        &self.public_key
+    }
+
+    /// Sign a playback JWT
+    ///
+    /// # Parameters
+    /// * `playback_id` - The playback ID to grant access to
+    /// * `expires_in` - How long the token stays valid
+    /// * `custom` - Optional custom claims, forwarded to the access-control webhook
+    ///
+    /// # Returns
+    /// * `Result<String, errors::Error>` - The ES256 JWT or an error
+    pub fn sign(
+        &self,
+        playback_id: &String,
+        expires_in: Duration,
+        custom: Option<Value>,
+    ) -> Result<String, errors::Error> {
+        let claims = PlaybackClaims {
+            sub: playback_id.to_string(),
+            action: String::from("pull"),
+            iss: self.issuer.clone(),
+            public_key: self.public_key.clone(),
+            video: String::from("none"),
+            exp: (now() + expires_in).as_secs(),
+            custom,
+        };
+        let header = serde_json::json!({ "alg": "ES256", "typ": "JWT" });
+        let payload = serde_json::to_vec(&claims).map_err(|_| errors::Error::INVALIDTOKEN)?;
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(payload)
+        );
+        let signature: Signature = self.signing_key.sign(signing_input.as_bytes());
+
+        Ok(format!(
+            "{}.{}",
+            signing_input,
+            URL_SAFE_NO_PAD.encode(signature.to_bytes())
+        ))
+    }
+
+    /// Sign a playback JWT and append it to a playback URL
+    ///
+    /// # Parameters
+    /// * `url` - The playback URL, e.g. a source from `PlaybackInfo`
+    /// * `playback_id` - The playback ID to grant access to
+    /// * `expires_in` - How long the token stays valid
+    /// * `custom` - Optional custom claims
+    ///
+    /// # Returns
+    /// * `Result<String, errors::Error>` - The URL with a `jwt` query parameter or an error
+    pub fn signed_url(
+        &self,
+        url: &String,
+        playback_id: &String,
+        expires_in: Duration,
+        custom: Option<Value>,
+    ) -> Result<String, errors::Error> {
+        let token = self.sign(playback_id, expires_in, custom)?;
+        let separator = if url.contains('?') { '&' } else { '?' };
+        Ok(format!("{}{}jwt={}", url, separator, token))
+    }
+
+    /// Signed HLS playback URL on the Livepeer CDN
+    pub fn signed_hls_url(
+        &self,
+        playback_id: &String,
+        expires_in: Duration,
+        custom: Option<Value>,
+    ) -> Result<String, errors::Error> {
+        let url = format!("{}/hls/{}/index.m3u8", PLAYBACK_CDN, playback_id);
+        self.signed_url(&url, playback_id, expires_in, custom)
+    }
+
+    /// Signed WebRTC (WHEP) playback URL on the Livepeer CDN
+    pub fn signed_webrtc_url(
+        &self,
+        playback_id: &String,
+        expires_in: Duration,
+        custom: Option<Value>,
+    ) -> Result<String, errors::Error> {
+        let url = format!("{}/webrtc/{}", PLAYBACK_CDN, playback_id);
+        self.signed_url(&url, playback_id, expires_in, custom)
+    }
+
+    /// Verify a token signed by this signer
+    pub fn verify(&self, token: &String) -> Result<PlaybackClaims, errors::Error> {
+        verify_playback_token(token, &self.public_key)
+    }
+}
+
+/// Verify a playback JWT against a public key
+///
+/// # Parameters
+/// * `token` - The JWT to verify
+/// * `public_key` - The public key, as base64 PEM returned by Studio or as plain PEM
+///
+/// # Returns
+/// * `Result<PlaybackClaims, errors::Error>` - The claims if the signature is valid and the token has not expired, or an error
+pub fn verify_playback_token(
+    token: &String,
+    public_key: &String,
+) -> Result<PlaybackClaims, errors::Error> {
+    let pem = decode_pem(public_key)?;
+    let verifying_key =
+        VerifyingKey::from_public_key_pem(&pem).map_err(|_| errors::Error::INVALIDSIGNINGKEY)?;
+
+    let parts: Vec<&str> = token.split('.').collect();
+    let (header, payload, signature) = match parts[..] {
+        [header, payload, signature] => (header, payload, signature),
+        _ => return Err(errors::Error::INVALIDTOKEN),
+    };
+
+    let header: Value = decode_segment(header)?;
+    if header["alg"] != "ES256" {
+        return Err(errors::Error::INVALIDTOKEN);
+    }
+
+    let signature = URL_SAFE_NO_PAD
+        .decode(signature)
+        .ok()
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or(errors::Error::INVALIDTOKEN)?;
+    verifying_key
+        .verify(format!("{}.{}", parts[0], payload).as_bytes(), &signature)
+        .map_err(|_| errors::Error::INVALIDTOKEN)?;
+
+    let claims: PlaybackClaims = decode_segment(payload)?;
+    if claims.exp <= now().as_secs() {
+        return Err(errors::Error::INVALIDTOKEN);
+    }
+    Ok(claims)
+}
+
+fn decode_segment<T: serde::de::DeserializeOwned>(segment: &str) -> Result<T, errors::Error> {
+    URL_SAFE_NO_PAD
+        .decode(segment)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .ok_or(errors::Error::INVALIDTOKEN)
+}
+
+/// Studio returns keys as base64 encoded PEM, accept both that and plain PEM
+fn decode_pem(key: &String) -> Result<String, errors::Error> {
+    if key.trim_start().starts_with("-----BEGIN") {
+        return Ok(key.to_string());
+    }
+    STANDARD
+        .decode(key.trim())
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .ok_or(errors::Error::INVALIDSIGNINGKEY)
+}
+
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
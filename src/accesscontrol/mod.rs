@@ -1,7 +1,25 @@
 pub mod api;
+pub mod jwt;
+pub mod webhook;
 
 pub trait AccessControl {
-    fn list_signing_keys(&self) -> Result<serde_json::Value, crate::errors::Error>;
-    fn create_signing_key(&self, name: String) -> Result<serde_json::Value, crate::errors::Error>;
+    fn list_signing_keys(
+        &self,
+    ) -> Result<crate::data::signing_key::SigningKeys, crate::errors::Error>;
+    fn create_signing_key(
+        &self,
+        name: String,
+    ) -> Result<crate::data::signing_key::SigningKey, crate::errors::Error>;
+    fn get_signing_key(
+        &self,
+        id: String,
+    ) -> Result<crate::data::signing_key::SigningKey, crate::errors::Error>;
+    fn update_signing_key(
+        &self,
+        id: String,
+        name: Option<String>,
+        disabled: Option<bool>,
+    ) -> Result<(), crate::errors::Error>;
+    fn disable_signing_key(&self, id: String) -> Result<(), crate::errors::Error>;
     fn delete_signing_key(&self, id: String) -> Result<serde_json::Value, crate::errors::Error>;
 }
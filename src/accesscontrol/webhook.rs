@@ -0,0 +1,272 @@
+use crate::errors;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Header carrying the webhook signature, formatted as `t=<timestamp>,v1=<hex hmac>`
+pub const SIGNATURE_HEADER: &str = "Livepeer-Signature";
+
+/// How far a signature timestamp may be from now before a signed request is rejected
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(5 * 60);
+
+/// Body Livepeer posts to the access-control webhook of a `webhook` playback policy
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GateRequest {
+    /// `accessKey` or `jwt`, depending on how the viewer authenticated
+    #[serde(rename = "type")]
+    pub rtype: Option<String>,
+    /// Access key the viewer passed to the player
+    pub access_key: Option<String>,
+    /// Public key of the signing key, for JWT playback
+    #[serde(rename = "pub")]
+    pub public_key: Option<String>,
+    /// The policy's `webhookContext`, or the JWT's custom claims
+    pub context: Option<Value>,
+    pub timestamp: Option<i64>,
+}
+
+/// Outcome of a gate request, as the HTTP status Livepeer expects
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GateResponse {
+    /// `200`, playback is allowed
+    Allow,
+    /// `403`, playback is denied
+    Deny,
+    /// `401`, the signature header is missing or invalid
+    InvalidSignature,
+    /// `400`, the body is not a gate request
+    BadRequest,
+}
+
+impl GateResponse {
+    pub fn status(&self) -> u16 {
+        match self {
+            GateResponse::Allow => 200,
+            GateResponse::Deny => 403,
+            GateResponse::InvalidSignature => 401,
+            GateResponse::BadRequest => 400,
+        }
+    }
+}
+
+/// Access-control gate running a closure to allow or deny viewers
+///
+/// `handle` is framework agnostic so the gate can be embedded in an existing server,
+/// `serve` runs it on its own listener.
+#[derive(Clone)]
+pub struct AccessControlGate {
+    handler: Arc<dyn Fn(&GateRequest) -> bool + Send + Sync>,
+    secret: Option<String>,
+    max_age: Option<Duration>,
+}
+
+impl AccessControlGate {
+    /// Create a new gate
+    ///
+    /// # Parameters
+    /// * `handler` - Returns `true` to allow playback for a request
+    ///
+    /// # Returns
+    /// * `Self` - A new gate accepting unsigned requests
+    pub fn new<F>(handler: F) -> Self
+    where
+        F: Fn(&GateRequest) -> bool + Send + Sync + 'static,
+    {
+        AccessControlGate {
+            handler: Arc::new(handler),
+            secret: None,
+            max_age: Some(DEFAULT_MAX_AGE),
+        }
+    }
+
+    /// Require requests to be signed with the webhook's shared secret
+    pub fn with_secret(mut self, secret: String) -> Self {
+        self.secret = Some(secret);
+        self
+    }
+
+    /// Set how far the signature timestamp may be from now, `None` to skip the check
+    ///
+    /// Defaults to `DEFAULT_MAX_AGE`, so a captured request can't be replayed later.
+    pub fn with_max_age(mut self, max_age: Option<Duration>) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Handle a gate request
+    ///
+    /// # Parameters
+    /// * `body` - The raw request body
+    /// * `signature` - The value of the `Livepeer-Signature` header, if any
+    ///
+    /// # Returns
+    /// * `GateResponse` - The response to send back to Livepeer
+    pub fn handle(&self, body: &[u8], signature: Option<&str>) -> GateResponse {
+        if let Some(secret) = &self.secret {
+            match signature {
+                Some(signature)
+                    if verify_signature(secret, body, signature)
+                        && self.is_fresh(signature_timestamp(signature)) => {}
+                _ => return GateResponse::InvalidSignature,
+            }
+        }
+
+        match serde_json::from_slice::<GateRequest>(body) {
+            Ok(request) if (self.handler)(&request) => GateResponse::Allow,
+            Ok(_) => GateResponse::Deny,
+            Err(_) => GateResponse::BadRequest,
+        }
+    }
+
+    fn is_fresh(&self, timestamp: Option<i64>) -> bool {
+        match (self.max_age, timestamp) {
+            (None, _) => true,
+            (Some(max_age), Some(timestamp)) => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |now| now.as_millis() as i64);
+                now.abs_diff(timestamp) <= max_age.as_millis() as u64
+            }
+            (Some(_), None) => false,
+        }
+    }
+
+    /// Serve the gate on a local address
+    ///
+    /// # Parameters
+    /// * `addr` - The address to listen on, e.g. `0.0.0.0:8080` or `127.0.0.1:0` for tests
+    ///
+    /// # Returns
+    /// * `Result<GateServer, errors::Error>` - A handle to the running server or an error
+    pub fn serve(self, addr: &str) -> Result<GateServer, errors::Error> {
+        let server =
+            Arc::new(tiny_http::Server::http(addr).map_err(|_| errors::Error::GATESERVER)?);
+        let local_addr = server
+            .server_addr()
+            .to_ip()
+            .ok_or(errors::Error::GATESERVER)?;
+
+        let listener = server.clone();
+        let thread = std::thread::spawn(move || {
+            for mut request in listener.incoming_requests() {
+                let mut body = Vec::new();
+                let response = if std::io::Read::read_to_end(request.as_reader(), &mut body).is_ok()
+                {
+                    let signature = request
+                        .headers()
+                        .iter()
+                        .find(|h| h.field.equiv(SIGNATURE_HEADER))
+                        .map(|h| h.value.as_str().to_string());
+                    self.handle(&body, signature.as_deref())
+                } else {
+                    GateResponse::BadRequest
+                };
+                let _ = request.respond(tiny_http::Response::empty(response.status()));
+            }
+        });
+
+        Ok(GateServer {
+            addr: local_addr,
+            server,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl std::fmt::Debug for AccessControlGate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AccessControlGate")
+            .field("signed", &self.secret.is_some())
+            .field("max_age", &self.max_age)
+            .finish()
+    }
+}
+
+/// Handle to a running gate server, stopped on drop
+pub struct GateServer {
+    pub addr: std::net::SocketAddr,
+    server: Arc<tiny_http::Server>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl GateServer {
+    /// URL to configure as the webhook endpoint
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Stop accepting requests and wait for the server thread to exit
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for GateServer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Sign a webhook body the way Livepeer does, mostly useful for tests
+///
+/// The HMAC-SHA256 covers the raw body only, the timestamp is sent alongside it.
+///
+/// # Parameters
+/// * `secret` - The webhook's shared secret
+/// * `body` - The raw request body
+/// * `timestamp` - Unix timestamp in milliseconds
+///
+/// # Returns
+/// * `String` - The `Livepeer-Signature` header value
+pub fn sign_payload(secret: &str, body: &[u8], timestamp: i64) -> String {
+    format!("t={},v1={}", timestamp, hmac_hex(secret, body))
+}
+
+/// Verify the HMAC of a `Livepeer-Signature` header against a body
+///
+/// The timestamp is not checked here, see `AccessControlGate::with_max_age`.
+pub fn verify_signature(secret: &str, body: &[u8], signature: &str) -> bool {
+    let expected = hmac_hex(secret, body);
+    signature
+        .split(',')
+        .filter_map(|part| part.trim().split_once('='))
+        .any(|(key, digest)| key == "v1" && constant_time_eq(digest, &expected))
+}
+
+/// Timestamp of a `Livepeer-Signature` header, in milliseconds
+pub fn signature_timestamp(signature: &str) -> Option<i64> {
+    signature
+        .split(',')
+        .filter_map(|part| part.trim().split_once('='))
+        .find(|(key, _)| *key == "t")
+        .and_then(|(_, t)| t.parse::<i64>().ok())
+}
+
+fn hmac_hex(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(body);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
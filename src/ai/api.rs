@@ -1,6 +1,22 @@
+use crate::ai::stream::LlmStream;
+use crate::data::generate::{
+    AudioToTextRequest, ImageResponse, ImageToImageRequest, ImageToTextRequest,
+    ImageToTextResponse, ImageToVideoRequest, LlmRequest, LlmResponse, MasksResponse, MediaInput,
+    SegmentAnything2Request, TextResponse, TextToImageRequest, UpscaleRequest, VideoResponse,
+};
 use crate::errors;
 use async_std;
 use serde_json;
+use std::time::Duration;
+
+/// Timeout for image pipelines
+pub const IMAGE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+/// Timeout for video pipelines, which can take several minutes per clip
+pub const VIDEO_TIMEOUT: Duration = Duration::from_secs(20 * 60);
+/// Timeout for transcription, which scales with the length of the media
+pub const AUDIO_TIMEOUT: Duration = Duration::from_secs(20 * 60);
+/// Timeout for LLM completions, covering the whole stream when streaming
+pub const LLM_TIMEOUT: Duration = Duration::from_secs(10 * 60);
 
 #[derive(Debug, Clone)]
 pub struct GenerateApi {
@@ -12,12 +28,183 @@ impl crate::ai::Generate for GenerateApi {
     /// Generate an image from text
     ///
     /// # Parameters
-    /// * `prompt` - Prompt for the image
+    /// * `request` - Prompt and generation parameters
     ///
     /// # Returns
-    /// * `Result<serde_json::Value, errors::Error>` - A JSON value containing the generate information or an error
-    fn text_to_image(&self, prompt: &String) -> Result<serde_json::Value, errors::Error> {
-        self.clone()._text_to_image(prompt)
+    /// * `Result<ImageResponse, errors::Error>` - The generated images or an error
+    fn text_to_image(&self, request: &TextToImageRequest) -> Result<ImageResponse, errors::Error> {
+        let output = self.clone()._text_to_image(request)?;
+        serde_json::from_value(output).map_err(|_| errors::Error::GENERATE)
+    }
+
+    /// Generate an image from an image and a prompt
+    ///
+    /// # Parameters
+    /// * `image` - The input image
+    /// * `request` - Prompt and generation parameters
+    ///
+    /// # Returns
+    /// * `Result<ImageResponse, errors::Error>` - The generated images or an error
+    fn image_to_image(
+        &self,
+        image: &MediaInput,
+        request: &ImageToImageRequest,
+    ) -> Result<ImageResponse, errors::Error> {
+        let output = self._multipart(
+            self.urls.generate.image_to_image,
+            "image",
+            image,
+            request,
+            IMAGE_TIMEOUT,
+        )?;
+        serde_json::from_value(output).map_err(|_| errors::Error::GENERATE)
+    }
+
+    /// Generate a video from an image
+    ///
+    /// # Parameters
+    /// * `image` - The input image
+    /// * `request` - Generation parameters
+    ///
+    /// # Returns
+    /// * `Result<VideoResponse, errors::Error>` - The generated videos or an error
+    fn image_to_video(
+        &self,
+        image: &MediaInput,
+        request: &ImageToVideoRequest,
+    ) -> Result<VideoResponse, errors::Error> {
+        let output = self._multipart(
+            self.urls.generate.image_to_video,
+            "image",
+            image,
+            request,
+            VIDEO_TIMEOUT,
+        )?;
+        serde_json::from_value(output).map_err(|_| errors::Error::GENERATE)
+    }
+
+    /// Upscale an image
+    ///
+    /// # Parameters
+    /// * `image` - The input image
+    /// * `request` - Prompt and generation parameters
+    ///
+    /// # Returns
+    /// * `Result<ImageResponse, errors::Error>` - The upscaled image or an error
+    fn upscale(
+        &self,
+        image: &MediaInput,
+        request: &UpscaleRequest,
+    ) -> Result<ImageResponse, errors::Error> {
+        let output = self._multipart(
+            self.urls.generate.upscale,
+            "image",
+            image,
+            request,
+            IMAGE_TIMEOUT,
+        )?;
+        serde_json::from_value(output).map_err(|_| errors::Error::GENERATE)
+    }
+
+    /// Transcribe an audio or video file
+    ///
+    /// # Parameters
+    /// * `audio` - The audio or video file
+    /// * `request` - Transcription parameters
+    ///
+    /// # Returns
+    /// * `Result<TextResponse, errors::Error>` - The transcript with timestamped chunks or an error
+    fn audio_to_text(
+        &self,
+        audio: &MediaInput,
+        request: &AudioToTextRequest,
+    ) -> Result<TextResponse, errors::Error> {
+        let output = self._multipart(
+            self.urls.generate.audio_to_text,
+            "audio",
+            audio,
+            request,
+            AUDIO_TIMEOUT,
+        )?;
+        serde_json::from_value(output).map_err(|_| errors::Error::GENERATE)
+    }
+
+    /// Generate a chat completion
+    ///
+    /// # Parameters
+    /// * `request` - Chat messages and generation parameters
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, errors::Error>` - The full response or an error
+    fn llm(&self, request: &LlmRequest) -> Result<LlmResponse, errors::Error> {
+        let output = self.clone()._llm(request)?;
+        serde_json::from_value(output).map_err(|_| errors::Error::GENERATE)
+    }
+
+    /// Generate a chat completion, streaming tokens as they are produced
+    ///
+    /// # Parameters
+    /// * `request` - Chat messages and generation parameters
+    ///
+    /// # Returns
+    /// * `Result<LlmStream, errors::Error>` - An iterator over the streamed chunks or an error
+    fn llm_stream(&self, request: &LlmRequest) -> Result<LlmStream, errors::Error> {
+        let mut body = serde_json::to_value(request).map_err(|_| errors::Error::GENERATE)?;
+        body["stream"] = serde_json::json!(true);
+        let deadline = std::time::Instant::now() + LLM_TIMEOUT;
+        let response = crate::utils::SurfRequest::post_stream(
+            format!("{}{}", self.client.config.host, self.urls.generate.llm),
+            body.to_string(),
+            self.client.clone(),
+            Some(LLM_TIMEOUT),
+        )?;
+        Ok(LlmStream::new(response).with_deadline(deadline))
+    }
+
+    /// Segment objects in an image from point or box prompts
+    ///
+    /// # Parameters
+    /// * `image` - The input image
+    /// * `request` - Point and box prompts
+    ///
+    /// # Returns
+    /// * `Result<MasksResponse, errors::Error>` - The masks and their scores or an error
+    fn segment_anything_2(
+        &self,
+        image: &MediaInput,
+        request: &SegmentAnything2Request,
+    ) -> Result<MasksResponse, errors::Error> {
+        let output = self._multipart(
+            self.urls.generate.segment_anything_2,
+            "image",
+            image,
+            request,
+            IMAGE_TIMEOUT,
+        )?;
+        serde_json::from_value(output).map_err(|_| errors::Error::GENERATE)
+    }
+
+    /// Caption an image
+    ///
+    /// # Parameters
+    /// * `image` - The input image
+    /// * `request` - Optional prompt and model
+    ///
+    /// # Returns
+    /// * `Result<ImageToTextResponse, errors::Error>` - The caption or an error
+    fn image_to_text(
+        &self,
+        image: &MediaInput,
+        request: &ImageToTextRequest,
+    ) -> Result<ImageToTextResponse, errors::Error> {
+        let output = self._multipart(
+            self.urls.generate.image_to_text,
+            "image",
+            image,
+            request,
+            IMAGE_TIMEOUT,
+        )?;
+        serde_json::from_value(output).map_err(|_| errors::Error::GENERATE)
     }
 }
 
@@ -39,27 +226,67 @@ impl GenerateApi {
     /// Text to image
     ///
     /// # Parameters
-    /// * `prompt` - Prompt for the image
+    /// * `request` - Prompt and generation parameters
     ///
     /// # Returns
     /// * `Result<serde_json::Value, errors::Error>` - A JSON value containing the generate information or an error
     pub fn _text_to_image(
         self: Self,
-        prompt: &String,
+        request: &TextToImageRequest,
     ) -> Result<serde_json::Value, errors::Error> {
-        let mut result: Result<serde_json::Value, errors::Error> = Err(errors::Error::CREATESTREAM);
-        let mut data = serde_json::json!({
-            "prompt": prompt,
-        });
-        let res: Result<serde_json::Value, errors::Error> = crate::utils::SurfRequest::post(
+        crate::utils::SurfRequest::post_with_timeout(
             format!("{}{}", self.client.config.host, self.urls.generate.text_to_image),
-            serde_json::to_string(&data).unwrap(),
+            serde_json::to_string(request).map_err(|_| errors::Error::GENERATE)?,
             self.client,
-        );
-        if res.is_ok() {
-            let output: serde_json::Value = serde_json::from_value(res.unwrap()).unwrap();
-            result = Ok(output)
-        }
-        result
+            IMAGE_TIMEOUT,
+        )
     }
-}
\ No newline at end of file
+
+    /// LLM chat completion, without streaming
+    ///
+    /// # Parameters
+    /// * `request` - Chat messages and generation parameters
+    ///
+    /// # Returns
+    /// * `Result<serde_json::Value, errors::Error>` - A JSON value containing the completion or an error
+    pub fn _llm(self: Self, request: &LlmRequest) -> Result<serde_json::Value, errors::Error> {
+        let mut body = serde_json::to_value(request).map_err(|_| errors::Error::GENERATE)?;
+        body["stream"] = serde_json::json!(false);
+        crate::utils::SurfRequest::post_with_timeout(
+            format!("{}{}", self.client.config.host, self.urls.generate.llm),
+            body.to_string(),
+            self.client,
+            LLM_TIMEOUT,
+        )
+    }
+
+    /// Post a media file and its parameters to a multipart pipeline
+    ///
+    /// # Parameters
+    /// * `path` - The pipeline URL path
+    /// * `field` - The form field carrying the media, e.g. `image`
+    /// * `media` - The media file
+    /// * `params` - The pipeline parameters, sent as text fields
+    /// * `timeout` - Request timeout
+    ///
+    /// # Returns
+    /// * `Result<serde_json::Value, errors::Error>` - A JSON value containing the generate information or an error
+    pub fn _multipart<T: serde::Serialize>(
+        &self,
+        path: &str,
+        field: &str,
+        media: &MediaInput,
+        params: &T,
+        timeout: Duration,
+    ) -> Result<serde_json::Value, errors::Error> {
+        let form = crate::utils::Multipart::new()
+            .fields(params)
+            .file(field, &media.file_name(), media.content_type(), &media.read()?);
+        crate::utils::SurfRequest::post_multipart(
+            format!("{}{}", self.client.config.host, path),
+            form,
+            self.client.clone(),
+            Some(timeout),
+        )
+    }
+}
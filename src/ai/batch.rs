@@ -0,0 +1,286 @@
+use crate::ai::Generate;
+use crate::data::generate::TextToImageRequest;
+use crate::errors;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// A text-to-image job, its outputs are written to a directory named after `id`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchJob {
+    pub id: String,
+    pub request: TextToImageRequest,
+}
+
+impl BatchJob {
+    pub fn new(id: &str, request: TextToImageRequest) -> Self {
+        BatchJob {
+            id: id.to_string(),
+            request,
+        }
+    }
+
+    /// Check that the ID names a single directory inside the output directory
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result or a `BATCHJOB` error describing the problem
+    pub fn validate(&self) -> Result<(), errors::Error> {
+        let mut components = Path::new(&self.id).components();
+        let single = matches!(
+            (components.next(), components.next()),
+            (Some(std::path::Component::Normal(_)), None)
+        );
+        if !single || self.id.contains(['/', '\\']) {
+            return Err(errors::Error::BATCHJOB(format!(
+                "job ID {:?} must be a plain directory name",
+                self.id
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchJobStatus {
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchImage {
+    /// Path relative to the output directory
+    pub file: PathBuf,
+    pub url: String,
+    pub seed: Option<u64>,
+    pub nsfw: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchJobResult {
+    pub request: TextToImageRequest,
+    pub status: BatchJobStatus,
+    pub attempts: u32,
+    #[serde(default)]
+    pub images: Vec<BatchImage>,
+    pub error: Option<String>,
+}
+
+/// Results of every job run in an output directory, keyed by job ID
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchManifest {
+    pub jobs: BTreeMap<String, BatchJobResult>,
+}
+
+impl BatchManifest {
+    /// Load the manifest of an output directory, empty if there is none yet
+    pub fn load(dir: &Path) -> Result<Self, errors::Error> {
+        let path = dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(BatchManifest::default());
+        }
+        let data = std::fs::read(path).map_err(|_| errors::Error::IO)?;
+        serde_json::from_slice(&data).map_err(|_| errors::Error::IO)
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<(), errors::Error> {
+        let data = serde_json::to_vec_pretty(self).map_err(|_| errors::Error::IO)?;
+        // Write then rename so an interrupted run never leaves a truncated manifest
+        let tmp = dir.join(format!("{}.tmp", MANIFEST_FILE));
+        std::fs::write(&tmp, data).map_err(|_| errors::Error::IO)?;
+        std::fs::rename(tmp, dir.join(MANIFEST_FILE)).map_err(|_| errors::Error::IO)
+    }
+
+    /// Whether a job already completed with the same parameters and its files are still there
+    pub fn is_done(&self, job: &BatchJob, dir: &Path) -> bool {
+        match self.jobs.get(&job.id) {
+            Some(result) => {
+                result.status == BatchJobStatus::Completed
+                    && result.request == job.request
+                    && result
+                        .images
+                        .iter()
+                        .all(|image| dir.join(&image.file).exists())
+            }
+            None => false,
+        }
+    }
+
+    pub fn completed(&self) -> usize {
+        self.jobs
+            .values()
+            .filter(|result| result.status == BatchJobStatus::Completed)
+            .count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.jobs
+            .values()
+            .filter(|result| result.status == BatchJobStatus::Failed)
+            .count()
+    }
+}
+
+/// Runs text-to-image jobs with bounded concurrency and retries
+///
+/// Images and a `manifest.json` of parameters and seeds are written to the output directory.
+/// Rerunning over the same directory skips jobs that already completed.
+#[derive(Debug, Clone)]
+pub struct BatchRunner {
+    pub api: crate::ai::api::GenerateApi,
+    pub output_dir: PathBuf,
+    /// Worker threads, at least 1, set with `with_concurrency`
+    concurrency: usize,
+    pub max_attempts: u32,
+    pub retry_delay: Duration,
+}
+
+impl BatchRunner {
+    /// Create a new runner
+    ///
+    /// # Parameters
+    /// * `api` - The generate API to run jobs with
+    /// * `output_dir` - The directory to write images and the manifest to
+    ///
+    /// # Returns
+    /// * `Self` - A runner with 4 workers and 3 attempts per job
+    pub fn new<P: AsRef<Path>>(api: &crate::ai::api::GenerateApi, output_dir: P) -> Self {
+        BatchRunner {
+            api: api.clone(),
+            output_dir: output_dir.as_ref().to_path_buf(),
+            concurrency: 4,
+            max_attempts: 3,
+            retry_delay: Duration::from_secs(2),
+        }
+    }
+
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Set the attempts per job, retries back off exponentially from `retry_delay`
+    pub fn with_retries(mut self, max_attempts: u32, retry_delay: Duration) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Run the jobs
+    ///
+    /// # Parameters
+    /// * `jobs` - The jobs to run, IDs must be unique and usable as directory names
+    ///
+    /// # Returns
+    /// * `Result<BatchManifest, errors::Error>` - The updated manifest, including failed jobs, or an error if a job ID is invalid or repeated, or the output directory can't be written
+    pub fn run(&self, jobs: Vec<BatchJob>) -> Result<BatchManifest, errors::Error> {
+        let mut ids = HashSet::new();
+        for job in &jobs {
+            job.validate()?;
+            // Jobs with the same ID would write over each other's images and manifest entry
+            if !ids.insert(job.id.as_str()) {
+                return Err(errors::Error::BATCHJOB(format!("duplicate job ID {}", job.id)));
+            }
+        }
+        std::fs::create_dir_all(&self.output_dir).map_err(|_| errors::Error::IO)?;
+        let manifest = BatchManifest::load(&self.output_dir)?;
+
+        let queue: VecDeque<BatchJob> = jobs
+            .into_iter()
+            .filter(|job| !manifest.is_done(job, &self.output_dir))
+            .collect();
+        let queue = Mutex::new(queue);
+        let manifest = Mutex::new(manifest);
+
+        std::thread::scope(|scope| {
+            for _ in 0..self.concurrency {
+                scope.spawn(|| loop {
+                    let job = match queue.lock().unwrap().pop_front() {
+                        Some(job) => job,
+                        None => break,
+                    };
+                    let result = self.run_job(&job);
+                    let mut manifest = manifest.lock().unwrap();
+                    manifest.jobs.insert(job.id, result);
+                    // Persist as we go so results survive a crash
+                    let _ = manifest.save(&self.output_dir);
+                });
+            }
+        });
+
+        let manifest = manifest.into_inner().unwrap();
+        manifest.save(&self.output_dir)?;
+        Ok(manifest)
+    }
+
+    fn run_job(&self, job: &BatchJob) -> BatchJobResult {
+        let mut attempts = 0;
+        let mut delay = self.retry_delay;
+        loop {
+            attempts += 1;
+            match self.generate(job) {
+                Ok(images) => {
+                    return BatchJobResult {
+                        request: job.request.clone(),
+                        status: BatchJobStatus::Completed,
+                        attempts,
+                        images,
+                        error: None,
+                    }
+                }
+                Err(e) => {
+                    if attempts >= self.max_attempts || !is_retryable(&e) {
+                        return BatchJobResult {
+                            request: job.request.clone(),
+                            status: BatchJobStatus::Failed,
+                            attempts,
+                            images: Vec::new(),
+                            error: Some(format!("{:?}", e)),
+                        };
+                    }
+                    std::thread::sleep(delay);
+                    delay *= 2;
+                }
+            }
+        }
+    }
+
+    fn generate(&self, job: &BatchJob) -> Result<Vec<BatchImage>, errors::Error> {
+        let response = self.api.text_to_image(&job.request)?;
+        let paths = response.download(&self.output_dir.join(&job.id))?;
+        Ok(response
+            .images
+            .iter()
+            .zip(paths)
+            .map(|(image, path)| BatchImage {
+                file: path
+                    .strip_prefix(&self.output_dir)
+                    .map(|p| p.to_path_buf())
+                    .unwrap_or(path),
+                url: image.url.clone(),
+                seed: image.seed,
+                nsfw: image.nsfw,
+            })
+            .collect())
+    }
+}
+
+/// Client errors won't succeed on a retry
+pub(crate) fn is_retryable(error: &errors::Error) -> bool {
+    !matches!(
+        error,
+        errors::Error::BADREQUEST
+            | errors::Error::UNAUTHORIZED
+            | errors::Error::FORBIDDEN
+            | errors::Error::NOTFOUND
+            | errors::Error::UNPROCESSABLEENTITY
+    )
+}
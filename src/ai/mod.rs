@@ -1,5 +1,48 @@
 pub mod api;
+pub mod batch;
+pub mod stream;
 
 pub trait Generate {
-    fn text_to_image(&self, prompt: &String) -> Result<serde_json::Value, crate::errors::Error>;
-}
\ No newline at end of file
+    fn text_to_image(
+        &self,
+        request: &crate::data::generate::TextToImageRequest,
+    ) -> Result<crate::data::generate::ImageResponse, crate::errors::Error>;
+    fn image_to_image(
+        &self,
+        image: &crate::data::generate::MediaInput,
+        request: &crate::data::generate::ImageToImageRequest,
+    ) -> Result<crate::data::generate::ImageResponse, crate::errors::Error>;
+    fn image_to_video(
+        &self,
+        image: &crate::data::generate::MediaInput,
+        request: &crate::data::generate::ImageToVideoRequest,
+    ) -> Result<crate::data::generate::VideoResponse, crate::errors::Error>;
+    fn upscale(
+        &self,
+        image: &crate::data::generate::MediaInput,
+        request: &crate::data::generate::UpscaleRequest,
+    ) -> Result<crate::data::generate::ImageResponse, crate::errors::Error>;
+    fn audio_to_text(
+        &self,
+        audio: &crate::data::generate::MediaInput,
+        request: &crate::data::generate::AudioToTextRequest,
+    ) -> Result<crate::data::generate::TextResponse, crate::errors::Error>;
+    fn llm(
+        &self,
+        request: &crate::data::generate::LlmRequest,
+    ) -> Result<crate::data::generate::LlmResponse, crate::errors::Error>;
+    fn llm_stream(
+        &self,
+        request: &crate::data::generate::LlmRequest,
+    ) -> Result<crate::ai::stream::LlmStream, crate::errors::Error>;
+    fn segment_anything_2(
+        &self,
+        image: &crate::data::generate::MediaInput,
+        request: &crate::data::generate::SegmentAnything2Request,
+    ) -> Result<crate::data::generate::MasksResponse, crate::errors::Error>;
+    fn image_to_text(
+        &self,
+        image: &crate::data::generate::MediaInput,
+        request: &crate::data::generate::ImageToTextRequest,
+    ) -> Result<crate::data::generate::ImageToTextResponse, crate::errors::Error>;
+}
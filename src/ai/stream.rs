@@ -0,0 +1,126 @@
+use crate::data::generate::LlmStreamChunk;
+use crate::errors;
+use async_std::io::prelude::BufReadExt;
+
+/// Incremental LLM output, parsed from a server-sent events body
+///
+/// Iterating blocks until the next event arrives and ends at `[DONE]` or at the end of the body.
+pub struct LlmStream {
+    reader: Box<dyn async_std::io::BufRead + Unpin + Send>,
+    deadline: Option<std::time::Instant>,
+    done: bool,
+}
+
+impl LlmStream {
+    /// Create a stream over an SSE body
+    ///
+    /// # Parameters
+    /// * `reader` - The body, e.g. a `surf::Response`
+    ///
+    /// # Returns
+    /// * `Self` - A new stream
+    pub fn new<R>(reader: R) -> Self
+    where
+        R: async_std::io::BufRead + Unpin + Send + 'static,
+    {
+        LlmStream {
+            reader: Box::new(reader),
+            deadline: None,
+            done: false,
+        }
+    }
+
+    /// Fail the stream if it hasn't finished by `deadline`
+    ///
+    /// # Parameters
+    /// * `deadline` - When reading gives up with `errors::Error::REQUEST`
+    ///
+    /// # Returns
+    /// * `Self` - The stream with the deadline set
+    pub fn with_deadline(mut self, deadline: std::time::Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Consume the rest of the stream into the full reply
+    pub fn text(self) -> Result<String, errors::Error> {
+        let mut text = String::new();
+        for chunk in self {
+            text.push_str(chunk?.text());
+        }
+        Ok(text)
+    }
+
+    /// Read the data of the next event, `None` at the end of the body
+    fn next_event(&mut self) -> Result<Option<String>, errors::Error> {
+        let mut data: Option<String> = None;
+        loop {
+            let mut line = String::new();
+            let read = match self.deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+                    async_std::task::block_on(async_std::future::timeout(
+                        remaining,
+                        self.reader.read_line(&mut line),
+                    ))
+                    .map_err(|_| errors::Error::REQUEST(String::from("LLM stream timed out")))?
+                }
+                None => async_std::task::block_on(self.reader.read_line(&mut line)),
+            }
+            .map_err(|_| errors::Error::IO)?;
+            if read == 0 {
+                return Ok(data);
+            }
+
+            let line = line.trim_end_matches(|c| c == '\n' || c == '\r');
+            if line.is_empty() {
+                if data.is_some() {
+                    return Ok(data);
+                }
+                continue;
+            }
+
+            // Comments, `event:`, `id:` and `retry:` fields carry nothing we use
+            if let Some(value) = line.strip_prefix("data:") {
+                let value = value.strip_prefix(' ').unwrap_or(value);
+                match &mut data {
+                    Some(data) => {
+                        data.push('\n');
+                        data.push_str(value);
+                    }
+                    None => data = Some(value.to_string()),
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for LlmStream {
+    type Item = Result<LlmStreamChunk, errors::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let event = match self.next_event() {
+            Ok(Some(event)) if event.trim() != "[DONE]" => event,
+            Ok(_) => {
+                self.done = true;
+                return None;
+            }
+            Err(e) => {
+                self.done = true;
+                return Some(Err(e));
+            }
+        };
+
+        Some(serde_json::from_str(&event).map_err(|_| errors::Error::GENERATE))
+    }
+}
+
+impl std::fmt::Debug for LlmStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LlmStream").field("done", &self.done).finish()
+    }
+}
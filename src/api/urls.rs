@@ -20,6 +20,13 @@ pub struct VodUrls {
 #[derive(Debug, Clone, Copy)]
 pub struct AiUrls {
     pub text_to_image: &'static str,
+    pub image_to_image: &'static str,
+    pub image_to_video: &'static str,
+    pub upscale: &'static str,
+    pub audio_to_text: &'static str,
+    pub llm: &'static str,
+    pub segment_anything_2: &'static str,
+    pub image_to_text: &'static str,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -72,6 +79,13 @@ impl LivepeerUrls {
 
         let generate = AiUrls {
             text_to_image: "/api/beta/generate/text-to-image",
+            image_to_image: "/api/beta/generate/image-to-image",
+            image_to_video: "/api/beta/generate/image-to-video",
+            upscale: "/api/beta/generate/upscale",
+            audio_to_text: "/api/beta/generate/audio-to-text",
+            llm: "/api/beta/generate/llm",
+            segment_anything_2: "/api/beta/generate/segment-anything-2",
+            image_to_text: "/api/beta/generate/image-to-text",
         };
 
         let urls = LivepeerUrls {
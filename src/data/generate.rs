@@ -0,0 +1,700 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextToImageRequest {
+    pub prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub negative_prompt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guidance_scale: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_inference_steps: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_images_per_prompt: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub safety_check: Option<bool>,
+}
+
+impl TextToImageRequest {
+    pub fn new(prompt: &String) -> Self {
+        TextToImageRequest {
+            prompt: prompt.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageToImageRequest {
+    pub prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub negative_prompt: Option<String>,
+    /// How much the input image is transformed, from 0 to 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strength: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guidance_scale: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_guidance_scale: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_inference_steps: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_images_per_prompt: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub safety_check: Option<bool>,
+}
+
+impl ImageToImageRequest {
+    pub fn new(prompt: &String) -> Self {
+        ImageToImageRequest {
+            prompt: prompt.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageToVideoRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fps: Option<u32>,
+    /// Amount of motion in the generated video
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub motion_bucket_id: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub noise_aug_strength: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_inference_steps: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub safety_check: Option<bool>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpscaleRequest {
+    pub prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_inference_steps: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub safety_check: Option<bool>,
+}
+
+impl UpscaleRequest {
+    pub fn new(prompt: &String) -> Self {
+        UpscaleRequest {
+            prompt: prompt.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioToTextRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_id: Option<String>,
+    /// `true` for segment timestamps, `word` for word level timestamps
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_timestamps: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LlmRequest {
+    pub messages: Vec<LlmMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+}
+
+impl LlmRequest {
+    pub fn new(messages: Vec<LlmMessage>) -> Self {
+        LlmRequest {
+            messages,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LlmMessage {
+    /// `system`, `user` or `assistant`, missing from streamed deltas after the first
+    #[serde(default)]
+    pub role: String,
+    #[serde(default)]
+    pub content: String,
+}
+
+impl LlmMessage {
+    pub fn system(content: &str) -> Self {
+        LlmMessage {
+            role: String::from("system"),
+            content: content.to_string(),
+        }
+    }
+
+    pub fn user(content: &str) -> Self {
+        LlmMessage {
+            role: String::from("user"),
+            content: content.to_string(),
+        }
+    }
+
+    pub fn assistant(content: &str) -> Self {
+        LlmMessage {
+            role: String::from("assistant"),
+            content: content.to_string(),
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LlmResponse {
+    pub id: Option<String>,
+    pub model: Option<String>,
+    pub created: Option<i64>,
+    pub usage: Option<LlmUsage>,
+    #[serde(default)]
+    pub choices: Vec<LlmChoice>,
+}
+
+/// Streamed events carry the same fields, with `delta` set instead of `message`
+pub type LlmStreamChunk = LlmResponse;
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LlmChoice {
+    #[serde(default)]
+    pub index: u32,
+    pub message: Option<LlmMessage>,
+    pub delta: Option<LlmMessage>,
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LlmUsage {
+    #[serde(default)]
+    pub prompt_tokens: u32,
+    #[serde(default)]
+    pub completion_tokens: u32,
+    #[serde(default)]
+    pub total_tokens: u32,
+}
+
+impl LlmResponse {
+    /// Content of the first choice, the full reply or a streamed token
+    pub fn text(&self) -> &str {
+        self.choices
+            .first()
+            .and_then(|choice| choice.message.as_ref().or(choice.delta.as_ref()))
+            .map(|message| message.content.as_str())
+            .unwrap_or("")
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SegmentAnything2Request {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_id: Option<String>,
+    /// Point prompts as `[x, y]` pixel coordinates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub point_coords: Option<Vec<[f64; 2]>>,
+    /// One label per point, `1` for foreground and `0` for background
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub point_labels: Option<Vec<u8>>,
+    /// Box prompt as `[x1, y1, x2, y2]`
+    #[serde(rename = "box", skip_serializing_if = "Option::is_none")]
+    pub box_coords: Option<[f64; 4]>,
+    /// Return several candidate masks instead of one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub multimask_output: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_logits: Option<bool>,
+    /// Whether the coordinates are normalized to 0..1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normalize_coords: Option<bool>,
+}
+
+impl SegmentAnything2Request {
+    /// Segment the objects under the given points
+    pub fn from_points(points: Vec<[f64; 2]>, labels: Vec<u8>) -> Self {
+        SegmentAnything2Request {
+            point_coords: Some(points),
+            point_labels: Some(labels),
+            ..Default::default()
+        }
+    }
+
+    /// Segment the object inside a box
+    pub fn from_box(box_coords: [f64; 4]) -> Self {
+        SegmentAnything2Request {
+            box_coords: Some(box_coords),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageToTextRequest {
+    /// Optional question or prefix to steer the caption
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_id: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageToTextResponse {
+    pub text: String,
+}
+
+/// Segmentation output, with each array serialized as a string by the pipeline
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MasksResponse {
+    pub masks: serde_json::Value,
+    pub scores: serde_json::Value,
+    #[serde(default)]
+    pub logits: serde_json::Value,
+}
+
+/// A decoded segmentation mask
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Mask {
+    pub width: usize,
+    pub height: usize,
+    /// Row-major, `true` where the pixel belongs to the object
+    pub data: Vec<bool>,
+}
+
+impl Mask {
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.data[y * self.width + x]
+    }
+
+    /// Number of pixels in the mask
+    pub fn area(&self) -> usize {
+        self.data.iter().filter(|set| **set).count()
+    }
+
+    /// Smallest `[x1, y1, x2, y2]` box containing the mask, inclusive
+    pub fn bounding_box(&self) -> Option<[usize; 4]> {
+        let mut bbox: Option<[usize; 4]> = None;
+        for (i, _) in self.data.iter().enumerate().filter(|(_, set)| **set) {
+            let (x, y) = (i % self.width, i / self.width);
+            bbox = Some(match bbox {
+                Some([x1, y1, x2, y2]) => [x1.min(x), y1.min(y), x2.max(x), y2.max(y)],
+                None => [x, y, x, y],
+            });
+        }
+        bbox
+    }
+
+    /// Encode as a binary PGM image, white where the mask is set
+    pub fn to_pgm(&self) -> Vec<u8> {
+        let mut out = format!("P5\n{} {}\n255\n", self.width, self.height).into_bytes();
+        out.extend(self.data.iter().map(|set| if *set { 255u8 } else { 0u8 }));
+        out
+    }
+}
+
+impl MasksResponse {
+    /// Decode the masks
+    ///
+    /// # Returns
+    /// * `Result<Vec<Mask>, crate::errors::Error>` - One mask per candidate or an error
+    pub fn decode_masks(&self) -> Result<Vec<Mask>, crate::errors::Error> {
+        let masks = parse_array(&self.masks)?;
+        // A single mask comes back as `[H][W]` rather than `[1][H][W]`
+        let masks = match masks.first().and_then(|row| row.as_array()) {
+            Some(row) if !row.iter().any(|v| v.is_array()) => vec![serde_json::Value::Array(masks)],
+            _ => masks,
+        };
+
+        masks
+            .iter()
+            .map(|mask| {
+                let rows = mask.as_array().ok_or(crate::errors::Error::GENERATE)?;
+                let width = rows
+                    .first()
+                    .and_then(|r| r.as_array())
+                    .map_or(0, |r| r.len());
+                let mut data = Vec::with_capacity(width * rows.len());
+                for row in rows {
+                    let row = row.as_array().ok_or(crate::errors::Error::GENERATE)?;
+                    if row.len() != width {
+                        return Err(crate::errors::Error::GENERATE);
+                    }
+                    // Binary masks and logits are both set above zero
+                    data.extend(row.iter().map(|v| match v {
+                        serde_json::Value::Bool(b) => *b,
+                        v => v.as_f64().unwrap_or(0.0) > 0.0,
+                    }));
+                }
+                Ok(Mask {
+                    width,
+                    height: rows.len(),
+                    data,
+                })
+            })
+            .collect()
+    }
+
+    /// Decode the predicted quality score of each mask
+    pub fn decode_scores(&self) -> Result<Vec<f64>, crate::errors::Error> {
+        parse_array(&self.scores)?
+            .iter()
+            .map(|v| v.as_f64().ok_or(crate::errors::Error::GENERATE))
+            .collect()
+    }
+
+    /// The mask with the highest score
+    pub fn best_mask(&self) -> Result<Option<Mask>, crate::errors::Error> {
+        let scores = self.decode_scores()?;
+        let best = scores
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(i, _)| i);
+        match best {
+            Some(i) => Ok(self.decode_masks()?.into_iter().nth(i)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Parse an array sent either as JSON or as a string holding a Python list
+fn parse_array(value: &serde_json::Value) -> Result<Vec<serde_json::Value>, crate::errors::Error> {
+    let value = match value {
+        serde_json::Value::String(s) => {
+            let json = s.replace("True", "true").replace("False", "false");
+            serde_json::from_str(&json).map_err(|_| crate::errors::Error::GENERATE)?
+        }
+        value => value.clone(),
+    };
+    match value {
+        serde_json::Value::Array(values) => Ok(values),
+        _ => Err(crate::errors::Error::GENERATE),
+    }
+}
+
+/// Media sent to a multipart generate pipeline
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaInput {
+    /// A file read from disk when the request is made
+    Path(std::path::PathBuf),
+    /// In-memory media, with the file name it is uploaded as
+    Bytes { file_name: String, data: Vec<u8> },
+}
+
+impl MediaInput {
+    pub fn from_path<P: AsRef<std::path::Path>>(path: P) -> Self {
+        MediaInput::Path(path.as_ref().to_path_buf())
+    }
+
+    pub fn from_bytes(file_name: &str, data: Vec<u8>) -> Self {
+        MediaInput::Bytes {
+            file_name: file_name.to_string(),
+            data,
+        }
+    }
+
+    pub fn file_name(&self) -> String {
+        match self {
+            MediaInput::Path(path) => path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            MediaInput::Bytes { file_name, .. } => file_name.clone(),
+        }
+    }
+
+    /// Content type guessed from the file extension
+    pub fn content_type(&self) -> &'static str {
+        let file_name = self.file_name().to_lowercase();
+        match file_name.rsplit_once('.').map(|(_, ext)| ext) {
+            Some("png") => "image/png",
+            Some("jpg") | Some("jpeg") => "image/jpeg",
+            Some("webp") => "image/webp",
+            Some("gif") => "image/gif",
+            Some("mp4") => "video/mp4",
+            Some("mov") => "video/quicktime",
+            Some("webm") => "video/webm",
+            Some("mp3") => "audio/mpeg",
+            Some("wav") => "audio/wav",
+            Some("m4a") => "audio/mp4",
+            Some("flac") => "audio/flac",
+            Some("ogg") => "audio/ogg",
+            _ => "application/octet-stream",
+        }
+    }
+
+    /// Read the media into memory
+    pub fn read(&self) -> Result<Vec<u8>, crate::errors::Error> {
+        match self {
+            MediaInput::Path(path) => std::fs::read(path).map_err(|_| crate::errors::Error::IO),
+            MediaInput::Bytes { data, .. } => Ok(data.clone()),
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageResponse {
+    pub images: Vec<Media>,
+}
+
+/// Image-to-video response
+///
+/// The API returns the clips under `images`, with the same url, seed and nsfw fields as images.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VideoResponse {
+    #[serde(rename = "images")]
+    pub videos: Vec<Media>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Media {
+    pub url: String,
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub nsfw: bool,
+}
+
+/// File extension for a media `Content-Type`, e.g. `mp4` for `video/mp4`
+pub fn content_type_extension(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next().unwrap_or("").trim();
+    match mime.to_ascii_lowercase().as_str() {
+        "image/png" => Some("png"),
+        "image/jpeg" => Some("jpg"),
+        "image/webp" => Some("webp"),
+        "image/gif" => Some("gif"),
+        "video/mp4" => Some("mp4"),
+        "video/webm" => Some("webm"),
+        "video/quicktime" => Some("mov"),
+        _ => None,
+    }
+}
+
+impl Media {
+    /// File extension of the media, taken from its URL
+    pub fn extension(&self) -> Option<&str> {
+        let path = self.url.split(|c| c == '?' || c == '#').next().unwrap_or("");
+        let file = path.rsplit('/').next().unwrap_or("");
+        match file.rsplit_once('.') {
+            Some((_, ext)) if !ext.is_empty() => Some(ext),
+            _ => None,
+        }
+    }
+
+    /// Download the media to a file
+    ///
+    /// # Parameters
+    /// * `path` - The file to write to
+    ///
+    /// # Returns
+    /// * `Result<(), crate::errors::Error>` - An empty result or an error
+    pub fn download(&self, path: &std::path::Path) -> Result<(), crate::errors::Error> {
+        let (_, bytes) = crate::utils::SurfRequest::download(self.url.clone())?;
+        std::fs::write(path, bytes).map_err(|_| crate::errors::Error::IO)
+    }
+
+    /// Download the media into a directory, as `<stem>.<extension>`
+    ///
+    /// The extension is taken from the URL, else from the response `Content-Type`, else `fallback`.
+    ///
+    /// # Parameters
+    /// * `dir` - The directory to write to
+    /// * `stem` - The file name without its extension
+    /// * `fallback` - The extension for the kind of media requested, e.g. `png`
+    ///
+    /// # Returns
+    /// * `Result<std::path::PathBuf, crate::errors::Error>` - The written file or an error
+    pub fn download_to(
+        &self,
+        dir: &std::path::Path,
+        stem: &str,
+        fallback: &str,
+    ) -> Result<std::path::PathBuf, crate::errors::Error> {
+        let (content_type, bytes) = crate::utils::SurfRequest::download(self.url.clone())?;
+        let extension = self
+            .extension()
+            .or_else(|| content_type.as_deref().and_then(content_type_extension))
+            .unwrap_or(fallback);
+        let path = dir.join(format!("{}.{}", stem, extension));
+        std::fs::write(&path, bytes).map_err(|_| crate::errors::Error::IO)?;
+        Ok(path)
+    }
+}
+
+impl ImageResponse {
+    /// Download all images to a directory
+    ///
+    /// Files are named after their index and seed, e.g. `0-12345.png`.
+    ///
+    /// # Parameters
+    /// * `dir` - The directory to write to, created if missing
+    ///
+    /// # Returns
+    /// * `Result<Vec<std::path::PathBuf>, crate::errors::Error>` - The written files or an error
+    pub fn download(
+        &self,
+        dir: &std::path::Path,
+    ) -> Result<Vec<std::path::PathBuf>, crate::errors::Error> {
+        std::fs::create_dir_all(dir).map_err(|_| crate::errors::Error::IO)?;
+        let mut paths = Vec::new();
+        for (i, image) in self.images.iter().enumerate() {
+            let stem = match image.seed {
+                Some(seed) => format!("{}-{}", i, seed),
+                None => i.to_string(),
+            };
+            paths.push(image.download_to(dir, &stem, "png")?);
+        }
+        Ok(paths)
+    }
+}
+
+impl VideoResponse {
+    /// Download all videos to a directory
+    ///
+    /// Files are named after their index and seed, e.g. `0-12345.mp4`.
+    ///
+    /// # Parameters
+    /// * `dir` - The directory to write to, created if missing
+    ///
+    /// # Returns
+    /// * `Result<Vec<std::path::PathBuf>, crate::errors::Error>` - The written files or an error
+    pub fn download(
+        &self,
+        dir: &std::path::Path,
+    ) -> Result<Vec<std::path::PathBuf>, crate::errors::Error> {
+        std::fs::create_dir_all(dir).map_err(|_| crate::errors::Error::IO)?;
+        let mut paths = Vec::new();
+        for (i, video) in self.videos.iter().enumerate() {
+            let stem = match video.seed {
+                Some(seed) => format!("{}-{}", i, seed),
+                None => i.to_string(),
+            };
+            paths.push(video.download_to(dir, &stem, "mp4")?);
+        }
+        Ok(paths)
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextResponse {
+    pub text: String,
+    #[serde(default)]
+    pub chunks: Vec<Chunk>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chunk {
+    /// Start and end in seconds, the end of the last chunk can be missing
+    pub timestamp: (f64, Option<f64>),
+    pub text: String,
+}
+
+/// Duration of a final chunk with no end timestamp
+const LAST_CUE_DURATION: f64 = 5.0;
+
+impl TextResponse {
+    /// Subtitle cues as `(start, end, text)`, with missing ends filled from the next chunk
+    fn cues(&self) -> Vec<(f64, f64, String)> {
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| !chunk.text.trim().is_empty())
+            .map(|(i, chunk)| {
+                let start = chunk.timestamp.0;
+                let end = chunk.timestamp.1.unwrap_or_else(|| {
+                    self.chunks
+                        .get(i + 1)
+                        .map(|next| next.timestamp.0)
+                        .unwrap_or(start + LAST_CUE_DURATION)
+                });
+                (start, end.max(start), chunk.text.trim().to_string())
+            })
+            .collect()
+    }
+
+    /// Render the chunks as WebVTT subtitles
+    pub fn to_webvtt(&self) -> String {
+        let mut out = String::from("WEBVTT\n");
+        for (start, end, text) in self.cues() {
+            out.push_str(&format!(
+                "\n{} --> {}\n{}\n",
+                format_timestamp(start, '.'),
+                format_timestamp(end, '.'),
+                text
+            ));
+        }
+        out
+    }
+
+    /// Render the chunks as SRT subtitles
+    pub fn to_srt(&self) -> String {
+        let mut out = String::new();
+        for (i, (start, end, text)) in self.cues().into_iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            out.push_str(&format!(
+                "{}\n{} --> {}\n{}\n",
+                i + 1,
+                format_timestamp(start, ','),
+                format_timestamp(end, ','),
+                text
+            ));
+        }
+        out
+    }
+
+    /// Write the chunks to a WebVTT file
+    pub fn write_webvtt(&self, path: &std::path::Path) -> Result<(), crate::errors::Error> {
+        std::fs::write(path, self.to_webvtt()).map_err(|_| crate::errors::Error::IO)
+    }
+
+    /// Write the chunks to an SRT file
+    pub fn write_srt(&self, path: &std::path::Path) -> Result<(), crate::errors::Error> {
+        std::fs::write(path, self.to_srt()).map_err(|_| crate::errors::Error::IO)
+    }
+}
+
+/// Format seconds as `HH:MM:SS.mmm`, with `separator` before the milliseconds
+pub(crate) fn format_timestamp(seconds: f64, separator: char) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        separator,
+        millis % 1000
+    )
+}
@@ -1 +1,4 @@
+pub mod generate;
+pub mod playback;
+pub mod signing_key;
 pub mod stream;
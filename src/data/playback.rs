@@ -0,0 +1,108 @@
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde_json::Value;
+
+pub const HLS_SOURCE_TYPE: &str = "html5/application/vnd.apple.mpegurl";
+pub const MP4_SOURCE_TYPE: &str = "html5/video/mp4";
+pub const WEBRTC_SOURCE_TYPE: &str = "html5/video/h264";
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaybackInfo {
+    #[serde(rename = "type")]
+    pub rtype: String,
+    pub meta: PlaybackMeta,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaybackMeta {
+    /// Studio reports this as `0`/`1` for streams and omits it for assets
+    #[serde(default, deserialize_with = "deserialize_live_flag")]
+    pub live: bool,
+    #[serde(default)]
+    pub source: Vec<PlaybackSource>,
+    pub playback_policy: Option<crate::data::stream::PlaybackPolicy>,
+    pub attestation: Option<Value>,
+    #[serde(default)]
+    pub dvr_playback: Vec<PlaybackSource>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaybackSource {
+    pub hrn: String,
+    #[serde(rename = "type")]
+    pub rtype: String,
+    pub url: String,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+    pub bitrate: Option<i64>,
+    pub size: Option<i64>,
+    pub error: Option<String>,
+}
+
+fn deserialize_live_flag<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<Value>::deserialize(deserializer)?;
+    Ok(match value {
+        Some(Value::Bool(b)) => b,
+        Some(Value::Number(n)) => n.as_i64().unwrap_or(0) != 0,
+        _ => false,
+    })
+}
+
+impl PlaybackSource {
+    pub fn is_hls(&self) -> bool {
+        self.rtype == HLS_SOURCE_TYPE
+    }
+
+    pub fn is_mp4(&self) -> bool {
+        self.rtype == MP4_SOURCE_TYPE
+    }
+
+    pub fn is_webrtc(&self) -> bool {
+        self.rtype == WEBRTC_SOURCE_TYPE
+    }
+}
+
+impl PlaybackInfo {
+    /// Whether the playback ID belongs to a live stream
+    pub fn is_live(&self) -> bool {
+        self.rtype == "live" || self.meta.live
+    }
+
+    /// Get the HLS source
+    ///
+    /// # Returns
+    /// * `Option<&PlaybackSource>` - The first HLS source, if any
+    pub fn hls_source(&self) -> Option<&PlaybackSource> {
+        self.meta.source.iter().find(|s| s.is_hls())
+    }
+
+    /// Get the best MP4 rendition at or below a resolution
+    ///
+    /// # Parameters
+    /// * `max_height` - The maximum height of the rendition
+    ///
+    /// # Returns
+    /// * `Option<&PlaybackSource>` - The tallest MP4 rendition fitting `max_height`, highest bitrate first on ties
+    pub fn mp4_source(&self, max_height: i64) -> Option<&PlaybackSource> {
+        self.meta
+            .source
+            .iter()
+            .filter(|s| s.is_mp4() && s.height.unwrap_or(0) <= max_height)
+            .max_by_key(|s| (s.height.unwrap_or(0), s.bitrate.unwrap_or(0)))
+    }
+
+    /// Get the WebRTC source
+    ///
+    /// # Returns
+    /// * `Option<&PlaybackSource>` - The first WebRTC (WHEP) source, if any
+    pub fn webrtc_source(&self) -> Option<&PlaybackSource> {
+        self.meta.source.iter().find(|s| s.is_webrtc())
+    }
+}
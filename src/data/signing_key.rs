@@ -0,0 +1,30 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+pub type SigningKeys = Vec<SigningKey>;
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningKey {
+    pub id: String,
+    pub name: Option<String>,
+    pub user_id: Option<String>,
+    pub project_id: Option<String>,
+    pub created_at: Option<i64>,
+    pub last_seen: Option<i64>,
+    /// Base64 encoded PEM public key
+    pub public_key: String,
+    /// Base64 encoded PEM private key, only returned when the key is created
+    pub private_key: Option<String>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+/// Fields to change on a signing key, the ones left `None` are kept as they are
+#[derive(Default, Debug, Clone, PartialEq, Serialize)]
+pub struct SigningKeyUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<bool>,
+}
@@ -36,11 +36,110 @@ pub struct Stream {
     pub playback_policy: Option<PlaybackPolicy>,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct PlaybackPolicy {
-    #[serde(rename = "type")]
-    pub rtype: String,
+// `remote = "Self"` derives inherent `serialize` and `deserialize` for the known types, which the
+// trait impls below fall back to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(remote = "Self", tag = "type", rename_all = "lowercase")]
+pub enum PlaybackPolicy {
+    #[serde(rename_all = "camelCase")]
+    Public {
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        allowed_origins: Vec<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Jwt {
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        allowed_origins: Vec<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Webhook {
+        webhook_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        webhook_context: Option<Value>,
+        /// Seconds between re-authorizations of an ongoing playback session
+        #[serde(skip_serializing_if = "Option::is_none")]
+        refresh_interval: Option<u64>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        allowed_origins: Vec<String>,
+    },
+    /// A policy type this client does not know about, kept as received so that sending it back
+    /// leaves the policy unchanged
+    #[serde(skip)]
+    Unknown(Value),
+}
+
+const KNOWN_POLICIES: &[&str] = &["public", "jwt", "webhook"];
+
+impl Serialize for PlaybackPolicy {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            PlaybackPolicy::Unknown(value) => value.serialize(serializer),
+            policy => PlaybackPolicy::serialize(policy, serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PlaybackPolicy {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        match value.get("type").and_then(Value::as_str) {
+            Some(kind) if KNOWN_POLICIES.contains(&kind) => {
+                PlaybackPolicy::deserialize(value).map_err(serde::de::Error::custom)
+            }
+            _ => Ok(PlaybackPolicy::Unknown(value)),
+        }
+    }
+}
+
+impl PlaybackPolicy {
+    pub fn public() -> Self {
+        PlaybackPolicy::Public {
+            allowed_origins: Vec::new(),
+        }
+    }
+
+    pub fn jwt() -> Self {
+        PlaybackPolicy::Jwt {
+            allowed_origins: Vec::new(),
+        }
+    }
+
+    pub fn webhook(
+        webhook_id: String,
+        webhook_context: Option<Value>,
+        refresh_interval: Option<u64>,
+    ) -> Self {
+        PlaybackPolicy::Webhook {
+            webhook_id,
+            webhook_context,
+            refresh_interval,
+            allowed_origins: Vec::new(),
+        }
+    }
+
+    /// Restrict playback to the given origins
+    pub fn with_allowed_origins(mut self, origins: Vec<String>) -> Self {
+        match &mut self {
+            PlaybackPolicy::Public { allowed_origins }
+            | PlaybackPolicy::Jwt { allowed_origins }
+            | PlaybackPolicy::Webhook {
+                allowed_origins, ..
+            } => *allowed_origins = origins,
+            PlaybackPolicy::Unknown(_) => {}
+        }
+        self
+    }
+
+    pub fn allowed_origins(&self) -> &[String] {
+        match self {
+            PlaybackPolicy::Public { allowed_origins }
+            | PlaybackPolicy::Jwt { allowed_origins }
+            | PlaybackPolicy::Webhook {
+                allowed_origins, ..
+            } => allowed_origins,
+            PlaybackPolicy::Unknown(_) => &[],
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
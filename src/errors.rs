@@ -16,6 +16,19 @@ pub enum Error {
     BADGATEWAY,
     LISTSTREAMS,
     CREATESTREAM,
+    PLAYBACKINFO,
+    INVALIDSIGNINGKEY,
+    INVALIDTOKEN,
+    SIGNINGKEY,
+    GATESERVER,
+    GENERATE,
+    IO,
+    FFMPEG(String),
+    PUSHOPTIONS(String),
+    WEBRTC(String),
+    REQUEST(String),
+    THUMBNAILOPTIONS(String),
+    BATCHJOB(String),
 }
 
 impl Error {
@@ -34,3 +47,15 @@ impl Error {
         }
     }
 }
+
+impl From<ffmpeg_next::Error> for Error {
+    fn from(error: ffmpeg_next::Error) -> Self {
+        Error::FFMPEG(error.to_string())
+    }
+}
+
+impl From<webrtc::Error> for Error {
+    fn from(error: webrtc::Error) -> Self {
+        Error::WEBRTC(error.to_string())
+    }
+}
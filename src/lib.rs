@@ -7,6 +7,7 @@ pub mod api;
 pub mod data;
 pub mod errors;
 pub mod live;
+pub mod media;
 pub mod playback;
 pub mod tests;
 pub mod user;
@@ -36,6 +37,8 @@ pub struct LivepeerConfig {
     /// User API token
     api_token: String,
     rtmp_endpoint: &'static str,
+    /// WebRTC (WHIP) ingest, the stream key is appended to it
+    whip_endpoint: &'static str,
 }
 
 /// Livepeer Client to interact with the livepeer.com API
@@ -55,6 +58,12 @@ pub struct Livepeer {
     pub user_api: user::UserApi,
     /// Rtmp push utils
     pub rtmp: live::rtmp::Rtmp,
+    /// SRT push utils
+    pub srt: live::srt::Srt,
+    /// WebRTC (WHIP) push utils
+    pub whip: live::whip::Whip,
+    /// HTTP segment push utils
+    pub http_push: live::http::HttpPush,
     /// Stream API set
     pub stream: live::stream::Stream,
     /// User Infos
@@ -71,19 +80,20 @@ impl LivepeerClient {
     /// * `api_token` - User API token
     /// * `env` - Livepeer Environment
     fn new(api_token: String, env: Option<LivepeerEnv>) -> Self {
-        let (host, rtmp_endpoint) = match env {
-            Some(LivepeerEnv::Box) => ("http://localhost:8888", "rtmp://localhost/live"),
-            Some(LivepeerEnv::Dev) => ("http://localhost:3004", "rtmp://127.0.0.1:1935/live"),
-            Some(LivepeerEnv::Stg) => ("https://livepeer.monster", "rtmp://rtmp.livepeer.monster:11935/live"),
-            Some(LivepeerEnv::Prod) => ("https://livepeer.com", "rtmp://rtmp.livepeer.com/live"),
-            Some(LivepeerEnv::Origin) | Some(LivepeerEnv::Test) => ("https://origin.livepeer.com", "rtmp://prg-playback.lp-playback.studio/live"),
-            None => ("https://livepeer.monster", "rtmp://rtmp.livepeer.monster:11935/live"),
+        let (host, rtmp_endpoint, whip_endpoint) = match env {
+            Some(LivepeerEnv::Box) => ("http://localhost:8888", "rtmp://localhost/live", "http://localhost:8888/webrtc"),
+            Some(LivepeerEnv::Dev) => ("http://localhost:3004", "rtmp://127.0.0.1:1935/live", "http://localhost:3004/webrtc"),
+            Some(LivepeerEnv::Stg) => ("https://livepeer.monster", "rtmp://rtmp.livepeer.monster:11935/live", "https://playback.livepeer.monster/webrtc"),
+            Some(LivepeerEnv::Prod) => ("https://livepeer.com", "rtmp://rtmp.livepeer.com/live", "https://playback.livepeer.studio/webrtc"),
+            Some(LivepeerEnv::Origin) | Some(LivepeerEnv::Test) => ("https://origin.livepeer.com", "rtmp://prg-playback.lp-playback.studio/live", "https://playback.livepeer.studio/webrtc"),
+            None => ("https://livepeer.monster", "rtmp://rtmp.livepeer.monster:11935/live", "https://playback.livepeer.monster/webrtc"),
         };
 
         let config = LivepeerConfig {
             host,
             api_token,
             rtmp_endpoint,
+            whip_endpoint,
         };
         LivepeerClient { config }
     }
@@ -110,6 +120,9 @@ impl Livepeer {
             access_control: accesscontrol::api::AccessControlApi::new(&client),
             stream: live::stream::Stream::new(&client),
             rtmp: live::rtmp::Rtmp { client: client.clone() },
+            srt: live::srt::Srt { client: client.clone() },
+            whip: live::whip::Whip { client: client.clone() },
+            http_push: live::http::HttpPush { client: client.clone() },
             user: user_info,
             playback: playback::api::PlaybackApi::new(&client),
             generate: ai::api::GenerateApi::new(&client),
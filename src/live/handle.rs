@@ -0,0 +1,472 @@
+use crate::errors;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How long `stop` waits for ffmpeg to finish the stream before killing it
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+/// ffmpeg output lines kept to explain a failure
+const STDERR_TAIL: usize = 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PushStatus {
+    Running,
+    /// The whole source was sent
+    Completed,
+    /// Ended early by `PushHandle::stop`
+    Stopped,
+    Failed(String),
+}
+
+/// A progress report, as printed by ffmpeg's `-progress` or measured by an in-process push
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct PushProgress {
+    pub frame: u64,
+    pub fps: f64,
+    /// Output bitrate in kbit/s, `None` until ffmpeg can tell
+    pub bitrate_kbps: Option<f64>,
+    /// Bytes sent so far
+    pub total_size: Option<u64>,
+    /// Media time sent so far
+    pub out_time: Duration,
+    pub dup_frames: u64,
+    pub drop_frames: u64,
+    /// Media time sent per wall-clock second, 1.0 for a realtime push
+    pub speed: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PushEvent {
+    /// The ingest accepted the connection and media is about to flow
+    Connected,
+    Progress(PushProgress),
+    /// A playlist moved on to an item, by its index in the playlist
+    ItemStarted { index: usize, path: String },
+    /// A playlist item couldn't be played and was skipped
+    ItemFailed {
+        index: usize,
+        path: String,
+        error: String,
+    },
+    /// A supervised push lost or couldn't open its connection and will retry
+    Reconnecting {
+        attempt: u32,
+        /// The endpoint the next attempt goes to
+        endpoint: String,
+        delay: Duration,
+        error: String,
+    },
+    /// An HTTP push sent a segment, or gave up on it
+    SegmentUploaded(crate::live::http::SegmentUpload),
+    Finished(PushStatus),
+}
+
+/// Parses the `key=value` blocks ffmpeg writes with `-progress`, each ended by a `progress=` line
+#[derive(Default, Debug)]
+pub struct ProgressParser {
+    current: PushProgress,
+}
+
+impl ProgressParser {
+    pub fn new() -> Self {
+        ProgressParser::default()
+    }
+
+    /// Feed one line of output
+    ///
+    /// # Parameters
+    /// * `line` - A line of `-progress` output
+    ///
+    /// # Returns
+    /// * `Option<PushProgress>` - The report, once its block is complete
+    pub fn push_line(&mut self, line: &str) -> Option<PushProgress> {
+        let (key, value) = line.trim().split_once('=')?;
+        let value = value.trim();
+        match key {
+            "frame" => self.current.frame = value.parse().unwrap_or(0),
+            "fps" => self.current.fps = value.parse().unwrap_or(0.0),
+            "bitrate" => {
+                self.current.bitrate_kbps = value.trim_end_matches("kbits/s").trim().parse().ok()
+            }
+            "total_size" => self.current.total_size = value.parse().ok(),
+            // Despite the name, `out_time_ms` is in microseconds too
+            "out_time_us" | "out_time_ms" => {
+                if let Ok(us) = value.parse::<i64>() {
+                    self.current.out_time = Duration::from_micros(us.max(0) as u64);
+                }
+            }
+            "dup_frames" => self.current.dup_frames = value.parse().unwrap_or(0),
+            "drop_frames" => self.current.drop_frames = value.parse().unwrap_or(0),
+            "speed" => self.current.speed = value.trim_end_matches('x').trim().parse().ok(),
+            "progress" => return Some(std::mem::take(&mut self.current)),
+            _ => {}
+        }
+        None
+    }
+}
+
+/// State shared between a handle and the thread doing the push
+#[derive(Debug)]
+struct Shared {
+    stop: AtomicBool,
+    progress: Mutex<Option<PushProgress>>,
+}
+
+/// The side of a push that does the work: checks for stop requests and reports progress
+#[derive(Debug, Clone)]
+pub(crate) struct PushControl {
+    shared: Arc<Shared>,
+    events: Option<Sender<PushEvent>>,
+}
+
+impl PushControl {
+    /// A control for a blocking push, that is never stopped and reports to no one
+    pub(crate) fn detached() -> Self {
+        PushControl {
+            shared: Arc::new(Shared {
+                stop: AtomicBool::new(false),
+                progress: Mutex::new(None),
+            }),
+            events: None,
+        }
+    }
+
+    pub(crate) fn is_stopped(&self) -> bool {
+        self.shared.stop.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn connected(&self) {
+        self.send(PushEvent::Connected);
+    }
+
+    pub(crate) fn send(&self, event: PushEvent) {
+        if let Some(events) = &self.events {
+            let _ = events.send(event);
+        }
+    }
+
+    /// Clear the latest report, returning it
+    pub(crate) fn take_progress(&self) -> Option<PushProgress> {
+        self.shared.progress.lock().unwrap().take()
+    }
+
+    pub(crate) fn report(&self, progress: PushProgress) {
+        *self.shared.progress.lock().unwrap() = Some(progress.clone());
+        if let Some(events) = &self.events {
+            let _ = events.send(PushEvent::Progress(progress));
+        }
+    }
+}
+
+/// Measures an in-process push, reporting at most once a second
+#[derive(Debug)]
+pub(crate) struct ProgressMeter {
+    started: Instant,
+    last_report: Instant,
+}
+
+impl ProgressMeter {
+    pub(crate) fn new() -> Self {
+        ProgressMeter {
+            started: Instant::now(),
+            last_report: Instant::now(),
+        }
+    }
+
+    pub(crate) fn is_due(&self) -> bool {
+        self.last_report.elapsed() >= Duration::from_secs(1)
+    }
+
+    /// Build a report from running totals
+    pub(crate) fn measure(
+        &mut self,
+        frames: u64,
+        dropped: u64,
+        bytes: u64,
+        out_time: f64,
+    ) -> PushProgress {
+        self.last_report = Instant::now();
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let out_time = out_time.max(0.0);
+        PushProgress {
+            frame: frames,
+            fps: if elapsed > 0.0 { frames as f64 / elapsed } else { 0.0 },
+            bitrate_kbps: (out_time > 0.0).then(|| bytes as f64 * 8.0 / out_time / 1000.0),
+            total_size: Some(bytes),
+            out_time: Duration::from_secs_f64(out_time),
+            dup_frames: 0,
+            drop_frames: dropped,
+            speed: (elapsed > 0.0).then(|| out_time / elapsed),
+        }
+    }
+}
+
+enum Backend {
+    Process {
+        child: Arc<Mutex<Child>>,
+        stdin: Option<ChildStdin>,
+        worker: Option<JoinHandle<(PushStatus, Option<ExitStatus>)>>,
+    },
+    Thread {
+        thread: Option<JoinHandle<PushStatus>>,
+    },
+}
+
+/// A push running in the background
+///
+/// Dropping a handle stops the push.
+pub struct PushHandle {
+    pub id: String,
+    backend: Backend,
+    shared: Arc<Shared>,
+    events: Receiver<PushEvent>,
+    status: PushStatus,
+    exit_status: Option<ExitStatus>,
+}
+
+impl PushHandle {
+    /// Spawn an ffmpeg command and follow its progress
+    ///
+    /// # Parameters
+    /// * `cmd` - The ffmpeg command, which must write `-progress pipe:1`
+    /// * `id` - An ID for the push
+    ///
+    /// # Returns
+    /// * `Result<PushHandle, errors::Error>` - A handle to the running process, or an `IO` error if it couldn't be started
+    pub fn spawn_process(mut cmd: Command, id: &str) -> Result<PushHandle, errors::Error> {
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|_| errors::Error::IO)?;
+
+        let (sender, events) = channel();
+        let shared = Arc::new(Shared {
+            stop: AtomicBool::new(false),
+            progress: Mutex::new(None),
+        });
+        let stdout = child.stdout.take();
+        let stderr_reader = child.stderr.take().map(|output| {
+            std::thread::spawn(move || {
+                let mut tail = VecDeque::new();
+                for line in BufReader::new(output).lines().map_while(Result::ok) {
+                    if tail.len() == STDERR_TAIL {
+                        tail.pop_front();
+                    }
+                    tail.push_back(line);
+                }
+                tail
+            })
+        });
+        let stdin = child.stdin.take();
+        let child = Arc::new(Mutex::new(child));
+
+        let control = PushControl {
+            shared: shared.clone(),
+            events: Some(sender),
+        };
+        let process = child.clone();
+        // Follows the progress until ffmpeg exits, then sends `Finished` and drops the last sender
+        let worker = std::thread::spawn(move || {
+            if let Some(stdout) = stdout {
+                let mut parser = ProgressParser::new();
+                let mut connected = false;
+                for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                    if let Some(progress) = parser.push_line(&line) {
+                        // ffmpeg only reports progress once the output is open
+                        if !connected {
+                            connected = true;
+                            control.connected();
+                        }
+                        control.report(progress);
+                    }
+                }
+            }
+            // Poll rather than block in `wait`, so `stop` can still take the lock to kill it
+            let exit = loop {
+                match process.lock().unwrap().try_wait() {
+                    Ok(None) => {}
+                    Ok(Some(exit)) => break Ok(exit),
+                    Err(_) => break Err(()),
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            };
+            let tail: Vec<String> = stderr_reader
+                .and_then(|reader| reader.join().ok())
+                .map(|tail| tail.into_iter().collect())
+                .unwrap_or_default();
+
+            let status = match exit {
+                _ if control.is_stopped() => PushStatus::Stopped,
+                Ok(exit) if exit.success() => PushStatus::Completed,
+                Ok(exit) => PushStatus::Failed(format!("ffmpeg {}: {}", exit, tail.join("\n"))),
+                Err(()) => PushStatus::Failed(String::from("couldn't wait for ffmpeg")),
+            };
+            control.send(PushEvent::Finished(status.clone()));
+            (status, exit.ok())
+        });
+
+        Ok(PushHandle {
+            id: id.to_string(),
+            backend: Backend::Process {
+                child,
+                stdin,
+                worker: Some(worker),
+            },
+            shared,
+            events,
+            status: PushStatus::Running,
+            exit_status: None,
+        })
+    }
+
+    /// Run an in-process push on its own thread
+    pub(crate) fn spawn_thread<F>(id: &str, push: F) -> PushHandle
+    where
+        F: FnOnce(PushControl) -> Result<(), errors::Error> + Send + 'static,
+    {
+        let (sender, events) = channel();
+        let shared = Arc::new(Shared {
+            stop: AtomicBool::new(false),
+            progress: Mutex::new(None),
+        });
+        let control = PushControl {
+            shared: shared.clone(),
+            events: Some(sender),
+        };
+        let thread = std::thread::spawn(move || {
+            let finish = control.clone();
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| push(control)));
+            let status = match result {
+                Ok(Ok(())) if finish.is_stopped() => PushStatus::Stopped,
+                Ok(Ok(())) => PushStatus::Completed,
+                Ok(Err(e)) => PushStatus::Failed(format!("{:?}", e)),
+                Err(_) => PushStatus::Failed(String::from("push thread panicked")),
+            };
+            // The last sender goes with `finish`, so `events` ends after this
+            finish.send(PushEvent::Finished(status.clone()));
+            status
+        });
+        PushHandle {
+            id: id.to_string(),
+            backend: Backend::Thread {
+                thread: Some(thread),
+            },
+            shared,
+            events,
+            status: PushStatus::Running,
+            exit_status: None,
+        }
+    }
+
+    /// The current status, without blocking
+    pub fn status(&mut self) -> PushStatus {
+        if self.status == PushStatus::Running {
+            let finished = match &self.backend {
+                Backend::Process { worker, .. } => worker.as_ref().map_or(true, |t| t.is_finished()),
+                Backend::Thread { thread } => thread.as_ref().map_or(true, |t| t.is_finished()),
+            };
+            if finished {
+                let _ = self.wait();
+            }
+        }
+        self.status.clone()
+    }
+
+    /// The latest progress report
+    pub fn progress(&self) -> Option<PushProgress> {
+        self.shared.progress.lock().unwrap().clone()
+    }
+
+    /// Progress and completion events, in order
+    ///
+    /// The last event is `Finished`, sent when the push ends, after which the channel disconnects.
+    pub fn events(&self) -> &Receiver<PushEvent> {
+        &self.events
+    }
+
+    /// The ffmpeg exit status, for a finished process push
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        self.exit_status
+    }
+
+    /// Stop the push, ending the stream cleanly where possible
+    ///
+    /// # Returns
+    /// * `Result<PushStatus, errors::Error>` - `Stopped`, or how the push ended if it was already done
+    pub fn stop(&mut self) -> Result<PushStatus, errors::Error> {
+        if self.status != PushStatus::Running {
+            return Ok(self.status.clone());
+        }
+        self.shared.stop.store(true, Ordering::SeqCst);
+        if let Backend::Process {
+            child,
+            stdin,
+            worker,
+        } = &mut self.backend
+        {
+            // `q` makes ffmpeg flush and close the connection, unlike a kill
+            if let Some(mut stdin) = stdin.take() {
+                let _ = stdin.write_all(b"q");
+            }
+            let deadline = Instant::now() + STOP_TIMEOUT;
+            while worker.as_ref().is_some_and(|t| !t.is_finished()) && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            let _ = child.lock().unwrap().kill();
+        }
+        self.wait()
+    }
+
+    /// Block until the push ends
+    ///
+    /// # Returns
+    /// * `Result<PushStatus, errors::Error>` - How the push ended, or an `IO` error if the process couldn't be waited on
+    pub fn wait(&mut self) -> Result<PushStatus, errors::Error> {
+        if self.status != PushStatus::Running {
+            return Ok(self.status.clone());
+        }
+        // The workers work out the status, and have already sent it as `Finished`
+        let status = match &mut self.backend {
+            Backend::Process { worker, .. } => {
+                let (status, exit) = worker
+                    .take()
+                    .ok_or(errors::Error::IO)?
+                    .join()
+                    .map_err(|_| errors::Error::IO)?;
+                self.exit_status = exit;
+                status
+            }
+            Backend::Thread { thread } => match thread.take().map(|t| t.join()) {
+                Some(Ok(status)) => status,
+                Some(Err(_)) => PushStatus::Failed(String::from("push thread panicked")),
+                None => PushStatus::Completed,
+            },
+        };
+        self.status = status.clone();
+        Ok(status)
+    }
+}
+
+impl Drop for PushHandle {
+    fn drop(&mut self) {
+        if self.status == PushStatus::Running {
+            let _ = self.stop();
+        }
+    }
+}
+
+impl std::fmt::Debug for PushHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PushHandle")
+            .field("id", &self.id)
+            .field("status", &self.status)
+            .finish()
+    }
+}
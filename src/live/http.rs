@@ -0,0 +1,340 @@
+#[derive(Debug, Clone)]
+pub struct HttpPush {
+    pub client: crate::LivepeerClient,
+}
+
+use crate::errors;
+use crate::live::handle::{PushControl, PushEvent, PushHandle};
+use crate::live::options::{HttpMethod, HttpPushOptions, PushOptions};
+use crate::live::rtmp::segment_file;
+use crate::utils::SurfRequest;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// How often the segment list is checked for finished segments
+const LIST_POLL: Duration = Duration::from_millis(100);
+
+/// A transcoded rendition of a segment, as returned by the ingest
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rendition {
+    /// The rendition profile, e.g. `P360p30fps16x9`
+    pub name: String,
+    pub content_type: String,
+    pub size: usize,
+}
+
+/// The outcome of sending one segment
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentUpload {
+    pub seq: u64,
+    pub duration: Duration,
+    pub bytes: usize,
+    /// Requests made, 1 when the first one succeeded
+    pub attempts: u32,
+    /// The renditions in the response, when `HttpPushOptions::renditions` is set
+    pub renditions: Vec<Rendition>,
+    /// Why the segment couldn't be sent after all retries
+    pub error: Option<String>,
+}
+
+impl SegmentUpload {
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Network and server errors are worth retrying, client errors will fail again
+fn is_retryable(error: &errors::Error) -> bool {
+    matches!(
+        error,
+        errors::Error::REQUEST(_)
+            | errors::Error::UNKNOWN
+            | errors::Error::INTERNALSERVERERROR
+            | errors::Error::BADGATEWAY
+    )
+}
+
+/// Split a `multipart/mixed` response into its renditions
+///
+/// # Parameters
+/// * `content_type` - The response `Content-Type`, with the boundary
+/// * `body` - The response body
+///
+/// # Returns
+/// * `Vec<Rendition>` - The parts, empty when the response isn't multipart
+pub fn parse_renditions(content_type: &str, body: &[u8]) -> Vec<Rendition> {
+    let boundary = match content_type
+        .split(';')
+        .filter_map(|param| param.trim().strip_prefix("boundary="))
+        .next()
+    {
+        Some(boundary) => format!("--{}", boundary.trim_matches('"')),
+        None => return vec![],
+    };
+
+    let mut renditions = Vec::new();
+    for part in split_bytes(body, boundary.as_bytes()).into_iter().skip(1) {
+        // The closing delimiter is the boundary followed by `--`
+        if part.starts_with(b"--") {
+            break;
+        }
+        let part = part.strip_prefix(b"\r\n").unwrap_or(part);
+        let (head, data) = match find_bytes(part, b"\r\n\r\n") {
+            Some(end) => (&part[..end], &part[end + 4..]),
+            None => continue,
+        };
+        let data = data.strip_suffix(b"\r\n").unwrap_or(data);
+        let head = String::from_utf8_lossy(head);
+        let header = |name: &str| {
+            head.lines().find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                key.trim()
+                    .eq_ignore_ascii_case(name)
+                    .then(|| value.trim().to_string())
+            })
+        };
+        let file_name = header("Content-Disposition").and_then(|disposition| {
+            disposition
+                .split(';')
+                .find_map(|param| param.trim().strip_prefix("filename="))
+                .map(|name| name.trim_matches('"').to_string())
+        });
+        renditions.push(Rendition {
+            name: header("Rendition-Name").or(file_name).unwrap_or_default(),
+            content_type: header("Content-Type").unwrap_or_default(),
+            size: data.len(),
+        });
+    }
+    renditions
+}
+
+fn find_bytes(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len()).position(|window| window == needle)
+}
+
+fn split_bytes<'a>(mut data: &'a [u8], separator: &[u8]) -> Vec<&'a [u8]> {
+    let mut pieces = Vec::new();
+    while let Some(at) = find_bytes(data, separator) {
+        pieces.push(&data[..at]);
+        data = &data[at + separator.len()..];
+    }
+    pieces.push(data);
+    pieces
+}
+
+/// Read the complete entries of a segment list, as file name, start and end in seconds
+fn read_segment_list(list: &Path) -> Vec<(String, f64, f64)> {
+    let contents = std::fs::read_to_string(list).unwrap_or_default();
+    contents
+        .split_inclusive('\n')
+        .filter(|line| line.ends_with('\n'))
+        .filter_map(|line| {
+            let mut fields = line.trim().rsplitn(3, ',');
+            let end = fields.next()?.parse().ok()?;
+            let start = fields.next()?.parse().ok()?;
+            Some((fields.next()?.to_string(), start, end))
+        })
+        .collect()
+}
+
+/// Live HTTP segment push Methods
+///
+impl HttpPush {
+    /// The URL a segment is sent to, e.g. `https://livepeer.com/live/<stream_key>/0.ts`
+    pub fn url(self: &Self, stream_key: &String, seq: u64, http_options: &HttpPushOptions) -> String {
+        let ingest = http_options
+            .ingest
+            .clone()
+            .unwrap_or_else(|| format!("{}/live", self.client.config.host));
+        format!("{}/{}/{}.ts", ingest, stream_key, seq)
+    }
+
+    /// Send one MPEG-TS segment, retrying network and server errors with backoff
+    ///
+    /// # Parameters
+    /// * `stream_key` - The stream key
+    /// * `seq` - The segment number, counting from 0
+    /// * `data` - The segment
+    /// * `duration` - The segment duration, sent as `Content-Duration`
+    /// * `http_options` - Method, retries and whether to ask for renditions
+    ///
+    /// # Returns
+    /// * `SegmentUpload` - The outcome, with the error if every attempt failed
+    pub fn upload_segment(
+        self: &Self,
+        stream_key: &String,
+        seq: u64,
+        data: Vec<u8>,
+        duration: Duration,
+        http_options: &HttpPushOptions,
+    ) -> SegmentUpload {
+        let url = self.url(stream_key, seq, http_options);
+        let method = match http_options.method {
+            HttpMethod::Put => surf::http::Method::Put,
+            HttpMethod::Post => surf::http::Method::Post,
+        };
+        let accept = if http_options.renditions {
+            "multipart/mixed"
+        } else {
+            "*/*"
+        };
+        let headers = [
+            ("Content-Type", String::from("video/mp2t")),
+            ("Content-Duration", duration.as_millis().to_string()),
+            ("Accept", String::from(accept)),
+        ];
+
+        let mut upload = SegmentUpload {
+            seq,
+            duration,
+            bytes: data.len(),
+            attempts: 0,
+            renditions: vec![],
+            error: None,
+        };
+        loop {
+            upload.attempts += 1;
+            match SurfRequest::send_bytes(
+                method,
+                url.clone(),
+                data.clone(),
+                &headers,
+                http_options.timeout,
+            ) {
+                Ok((content_type, body)) => {
+                    upload.renditions = parse_renditions(&content_type.unwrap_or_default(), &body);
+                    upload.error = None;
+                    return upload;
+                }
+                Err(e) => {
+                    upload.error = Some(format!("{:?}", e));
+                    if !is_retryable(&e) || upload.attempts > http_options.retries {
+                        return upload;
+                    }
+                    std::thread::sleep(http_options.backoff.delay(upload.attempts));
+                }
+            }
+        }
+    }
+
+    /// Segment a file and send the segments to the HTTP ingest in order, in realtime
+    ///
+    /// Video is transcoded like `Rtmp::push_with_options`. A segment that still fails after its
+    /// retries is reported and skipped, so a live stream keeps going.
+    ///
+    /// # Parameters
+    /// * `stream_key` - The stream key
+    /// * `file_path` - The file to push
+    /// * `options` - Encoder settings, validated before anything is sent
+    /// * `http_options` - Segmenting and upload settings, validated before anything is sent
+    ///
+    /// # Returns
+    /// * `Result<Vec<SegmentUpload>, errors::Error>` - The outcome of every segment, or an error if the file couldn't be segmented
+    pub fn push(
+        self: &Self,
+        stream_key: &String,
+        file_path: &String,
+        options: &PushOptions,
+        http_options: &HttpPushOptions,
+    ) -> Result<Vec<SegmentUpload>, errors::Error> {
+        options.validate()?;
+        http_options.validate(options)?;
+        self.push_segments(stream_key, file_path, options, http_options, &PushControl::detached())
+    }
+
+    /// Segment a file and send the segments to the HTTP ingest, in the background
+    ///
+    /// The handle reports `PushEvent::SegmentUploaded` for every segment.
+    ///
+    /// # Parameters
+    /// * `stream_key` - The stream key
+    /// * `file_path` - The file to push
+    /// * `options` - Encoder settings, validated before the push starts
+    /// * `http_options` - Segmenting and upload settings, validated before the push starts
+    ///
+    /// # Returns
+    /// * `Result<PushHandle, errors::Error>` - A handle to the push, or a `PUSHOPTIONS` error
+    pub fn spawn(
+        self: &Self,
+        stream_key: &String,
+        file_path: &String,
+        options: &PushOptions,
+        http_options: &HttpPushOptions,
+    ) -> Result<PushHandle, errors::Error> {
+        options.validate()?;
+        http_options.validate(options)?;
+        let push = self.clone();
+        let key = stream_key.clone();
+        let file_path = file_path.clone();
+        let options = options.clone();
+        let http_options = http_options.clone();
+        Ok(PushHandle::spawn_thread(stream_key, move |control| {
+            push.push_segments(&key, &file_path, &options, &http_options, &control)
+                .map(|_| ())
+        }))
+    }
+
+    /// Segment into a temporary directory while a second thread sends finished segments
+    fn push_segments(
+        self: &Self,
+        stream_key: &String,
+        file_path: &String,
+        options: &PushOptions,
+        http_options: &HttpPushOptions,
+        control: &PushControl,
+    ) -> Result<Vec<SegmentUpload>, errors::Error> {
+        let dir = std::env::temp_dir().join(format!("livepeer-rs-{:x}", rand::random::<u64>()));
+        std::fs::create_dir_all(&dir).map_err(|_| errors::Error::IO)?;
+        let list = dir.join("segments.csv");
+        let segmented = AtomicBool::new(false);
+
+        let result = std::thread::scope(|scope| {
+            let uploader = scope.spawn(|| {
+                let mut uploads: Vec<SegmentUpload> = Vec::new();
+                loop {
+                    // Checked before reading the list, so the last segments aren't missed
+                    let finished = segmented.load(Ordering::SeqCst);
+                    for (name, start, end) in read_segment_list(&list).into_iter().skip(uploads.len()) {
+                        let path = dir.join(&name);
+                        let seq = uploads.len() as u64;
+                        let duration = Duration::from_secs_f64((end - start).max(0.0));
+                        let upload = match std::fs::read(&path) {
+                            Ok(data) => {
+                                self.upload_segment(stream_key, seq, data, duration, http_options)
+                            }
+                            Err(e) => SegmentUpload {
+                                seq,
+                                duration,
+                                bytes: 0,
+                                attempts: 0,
+                                renditions: vec![],
+                                error: Some(e.to_string()),
+                            },
+                        };
+                        let _ = std::fs::remove_file(&path);
+                        control.send(PushEvent::SegmentUploaded(upload.clone()));
+                        uploads.push(upload);
+                    }
+                    if finished {
+                        return uploads;
+                    }
+                    std::thread::sleep(LIST_POLL);
+                }
+            });
+            let result = segment_file(
+                file_path,
+                &dir,
+                &list,
+                http_options.segment_duration,
+                options,
+                control,
+            );
+            segmented.store(true, Ordering::SeqCst);
+            let uploads = uploader.join().unwrap_or_default();
+            result.map(|_| uploads)
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+        result
+    }
+}
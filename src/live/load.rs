@@ -0,0 +1,394 @@
+use crate::errors;
+use crate::live::handle::{PushEvent, PushHandle, PushProgress, PushStatus};
+use crate::live::options::PushOptions;
+use crate::live::pattern::TestPattern;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How often a push is checked for having ended on its own
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What each stream of a load test pushes
+#[derive(Debug, Clone)]
+pub enum LoadSource {
+    /// A media file, transcoded on every push
+    File(String),
+    /// A generated pattern, which runs for the whole test whatever its duration
+    Pattern(TestPattern),
+}
+
+/// Metrics of one push of a load test
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PushMetrics {
+    pub index: usize,
+    pub stream_name: String,
+    pub stream_id: Option<String>,
+    /// When the push started, relative to the start of the test
+    pub started_at_ms: u64,
+    /// Time from starting the push to the ingest accepting it
+    pub connect_time_ms: Option<u64>,
+    /// Media time sent
+    pub pushed_secs: f64,
+    pub frames: u64,
+    pub dropped_frames: u64,
+    pub bytes: u64,
+    /// Bitrate over the whole push
+    pub avg_bitrate_kbps: Option<f64>,
+    /// Lowest bitrate between two progress reports, the rate the push sustained
+    pub min_bitrate_kbps: Option<f64>,
+    /// Lowest media time sent per wall-clock second, below 1.0 the push fell behind realtime
+    pub min_speed: Option<f64>,
+    /// `completed`, `stopped` or `failed`
+    pub status: String,
+    pub error: Option<String>,
+    /// Whether the stream was deleted after the test
+    pub deleted: bool,
+}
+
+impl PushMetrics {
+    pub(crate) fn new(index: usize, stream_name: &str) -> Self {
+        PushMetrics {
+            index,
+            stream_name: stream_name.to_string(),
+            stream_id: None,
+            started_at_ms: 0,
+            connect_time_ms: None,
+            pushed_secs: 0.0,
+            frames: 0,
+            dropped_frames: 0,
+            bytes: 0,
+            avg_bitrate_kbps: None,
+            min_bitrate_kbps: None,
+            min_speed: None,
+            status: String::from("failed"),
+            error: None,
+            deleted: false,
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.status != "failed"
+    }
+
+    /// Fold in a progress report, following on from `previous`
+    pub(crate) fn record(&mut self, progress: &PushProgress, previous: Option<&PushProgress>) {
+        self.pushed_secs = progress.out_time.as_secs_f64();
+        self.frames = progress.frame;
+        self.dropped_frames = progress.drop_frames;
+        self.bytes = progress.total_size.unwrap_or(self.bytes);
+        self.avg_bitrate_kbps = progress.bitrate_kbps.or(self.avg_bitrate_kbps);
+        if let Some(speed) = progress.speed {
+            self.min_speed = Some(self.min_speed.map_or(speed, |min| min.min(speed)));
+        }
+        if let Some(previous) = previous {
+            let seconds = (progress.out_time.saturating_sub(previous.out_time)).as_secs_f64();
+            let bytes = progress
+                .total_size
+                .unwrap_or(0)
+                .saturating_sub(previous.total_size.unwrap_or(0));
+            if seconds > 0.0 {
+                let kbps = bytes as f64 * 8.0 / seconds / 1000.0;
+                self.min_bitrate_kbps =
+                    Some(self.min_bitrate_kbps.map_or(kbps, |min| min.min(kbps)));
+            }
+        }
+    }
+
+    /// Set the status and error from how the push ended
+    pub(crate) fn finish(&mut self, status: PushStatus) {
+        match status {
+            PushStatus::Completed | PushStatus::Running => self.status = String::from("completed"),
+            PushStatus::Stopped => self.status = String::from("stopped"),
+            PushStatus::Failed(e) => {
+                self.status = String::from("failed");
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+/// The results of a load test
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoadReport {
+    /// Unix time the test started, in seconds
+    pub started_at: u64,
+    pub streams: usize,
+    pub ramp_up_secs: f64,
+    pub duration_secs: f64,
+    pub pushes: Vec<PushMetrics>,
+}
+
+impl LoadReport {
+    /// Number of pushes that connected
+    pub fn connected(&self) -> usize {
+        self.pushes
+            .iter()
+            .filter(|push| push.connect_time_ms.is_some())
+            .count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.pushes.iter().filter(|push| !push.is_ok()).count()
+    }
+
+    /// Average connect time of the pushes that connected
+    pub fn avg_connect_time_ms(&self) -> Option<f64> {
+        let times: Vec<u64> = self
+            .pushes
+            .iter()
+            .filter_map(|push| push.connect_time_ms)
+            .collect();
+        (!times.is_empty()).then(|| times.iter().sum::<u64>() as f64 / times.len() as f64)
+    }
+
+    pub fn to_json(&self) -> Result<String, errors::Error> {
+        serde_json::to_string_pretty(self).map_err(|_| errors::Error::IO)
+    }
+
+    /// One row per push
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "index,stream_name,stream_id,started_at_ms,connect_time_ms,pushed_secs,frames,dropped_frames,bytes,avg_bitrate_kbps,min_bitrate_kbps,min_speed,status,error,deleted\n",
+        );
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        for push in &self.pushes {
+            let row = [
+                push.index.to_string(),
+                csv_field(&push.stream_name),
+                csv_field(&optional(push.stream_id.clone())),
+                push.started_at_ms.to_string(),
+                optional(push.connect_time_ms.map(|ms| ms.to_string())),
+                format!("{:.3}", push.pushed_secs),
+                push.frames.to_string(),
+                push.dropped_frames.to_string(),
+                push.bytes.to_string(),
+                optional(push.avg_bitrate_kbps.map(|kbps| format!("{:.1}", kbps))),
+                optional(push.min_bitrate_kbps.map(|kbps| format!("{:.1}", kbps))),
+                optional(push.min_speed.map(|speed| format!("{:.3}", speed))),
+                push.status.clone(),
+                csv_field(&optional(push.error.clone())),
+                push.deleted.to_string(),
+            ];
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> Result<(), errors::Error> {
+        std::fs::write(path, self.to_json()?).map_err(|_| errors::Error::IO)
+    }
+
+    pub fn write_csv<P: AsRef<Path>>(&self, path: P) -> Result<(), errors::Error> {
+        std::fs::write(path, self.to_csv()).map_err(|_| errors::Error::IO)
+    }
+}
+
+/// Quote a CSV field if it needs it
+fn csv_field(value: &str) -> String {
+    if value.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Pushes to many new streams at once and measures how ingest holds up
+///
+/// Every stream is created for the test and deleted afterwards, whether its push worked or not.
+#[derive(Debug, Clone)]
+pub struct LoadTest {
+    pub rtmp: crate::live::rtmp::Rtmp,
+    pub stream: crate::live::stream::Stream,
+    pub streams: usize,
+    /// Time between the first and the last push starting
+    pub ramp_up: Duration,
+    /// How long each push runs for, file pushes stop earlier at the end of the file
+    pub duration: Duration,
+    pub source: LoadSource,
+    pub options: PushOptions,
+    /// Streams are named `<name_prefix>-<index>`
+    pub name_prefix: String,
+}
+
+impl LoadTest {
+    /// Create a new load test
+    ///
+    /// # Parameters
+    /// * `rtmp` - The RTMP utils to push with
+    /// * `stream` - The stream API to create and delete streams with
+    /// * `streams` - Number of streams to push to
+    /// * `source` - What to push
+    ///
+    /// # Returns
+    /// * `Self` - A test ramping up over 10s and pushing for 60s
+    pub fn new(
+        rtmp: &crate::live::rtmp::Rtmp,
+        stream: &crate::live::stream::Stream,
+        streams: usize,
+        source: LoadSource,
+    ) -> Self {
+        LoadTest {
+            rtmp: rtmp.clone(),
+            stream: stream.clone(),
+            streams,
+            ramp_up: Duration::from_secs(10),
+            duration: Duration::from_secs(60),
+            source,
+            options: PushOptions::default(),
+            name_prefix: String::from("load-test"),
+        }
+    }
+
+    pub fn with_ramp_up(mut self, ramp_up: Duration) -> Self {
+        self.ramp_up = ramp_up;
+        self
+    }
+
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    pub fn with_options(mut self, options: PushOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Run the test
+    ///
+    /// # Returns
+    /// * `Result<LoadReport, errors::Error>` - The report, including failed pushes, or an error if the options are invalid
+    pub fn run(&self) -> Result<LoadReport, errors::Error> {
+        self.options.validate()?;
+        if let LoadSource::Pattern(pattern) = &self.source {
+            pattern.validate()?;
+        }
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        // Create every stream up front so API latency doesn't skew the ramp
+        let mut pushes: Vec<(PushMetrics, Option<String>)> = (0..self.streams)
+            .map(|index| self.create_stream(index))
+            .collect();
+
+        let started = Instant::now();
+        let stagger = match self.streams {
+            0 | 1 => Duration::ZERO,
+            n => self.ramp_up / (n as u32 - 1),
+        };
+        std::thread::scope(|scope| {
+            for (i, (metrics, stream_key)) in pushes.iter_mut().enumerate() {
+                scope.spawn(move || {
+                    let start = started + stagger * i as u32;
+                    std::thread::sleep(start.saturating_duration_since(Instant::now()));
+                    metrics.started_at_ms = started.elapsed().as_millis() as u64;
+                    if let Some(stream_key) = stream_key {
+                        self.push(stream_key, metrics);
+                    }
+                });
+            }
+        });
+
+        for (metrics, _) in pushes.iter_mut() {
+            if let Some(stream_id) = &metrics.stream_id {
+                metrics.deleted = self.stream.clone().delete_stream(stream_id.clone()).is_ok();
+            }
+        }
+
+        Ok(LoadReport {
+            started_at,
+            streams: self.streams,
+            ramp_up_secs: self.ramp_up.as_secs_f64(),
+            duration_secs: self.duration.as_secs_f64(),
+            pushes: pushes.into_iter().map(|(metrics, _)| metrics).collect(),
+        })
+    }
+
+    /// Create the stream of a push, returning its stream key
+    fn create_stream(&self, index: usize) -> (PushMetrics, Option<String>) {
+        let name = format!("{}-{}", self.name_prefix, index);
+        let mut metrics = PushMetrics::new(index, &name);
+        let stream_id = match self.stream.clone().create_stream(&name, &vec![], None) {
+            Ok(stream_id) => stream_id,
+            Err(e) => {
+                metrics.error = Some(format!("create stream: {:?}", e));
+                return (metrics, None);
+            }
+        };
+        metrics.stream_id = Some(stream_id.clone());
+        match self.stream.clone().get_stream_by_id(stream_id) {
+            Ok(stream) => match stream["streamKey"].as_str() {
+                Some(stream_key) => (metrics, Some(stream_key.to_string())),
+                None => {
+                    metrics.error = Some(String::from("stream has no stream key"));
+                    (metrics, None)
+                }
+            },
+            Err(e) => {
+                metrics.error = Some(format!("get stream: {:?}", e));
+                (metrics, None)
+            }
+        }
+    }
+
+    /// Push to a stream for the test duration, recording metrics as it goes
+    fn push(&self, stream_key: &String, metrics: &mut PushMetrics) {
+        let spawned = Instant::now();
+        let handle = match &self.source {
+            LoadSource::File(path) => self.rtmp.spawn(stream_key, path, &self.options),
+            LoadSource::Pattern(pattern) => self.rtmp.spawn_test_pattern(
+                stream_key,
+                &pattern.clone().with_duration(None),
+                &self.options,
+            ),
+        };
+        let mut handle: PushHandle = match handle {
+            Ok(handle) => handle,
+            Err(e) => {
+                metrics.error = Some(format!("{:?}", e));
+                return;
+            }
+        };
+
+        let deadline = spawned + self.duration;
+        let mut previous: Option<PushProgress> = None;
+        loop {
+            let now = Instant::now();
+            if now >= deadline || handle.status() != PushStatus::Running {
+                break;
+            }
+            match handle.events().recv_timeout((deadline - now).min(POLL_INTERVAL)) {
+                Ok(PushEvent::Connected) => {
+                    metrics.connect_time_ms = Some(spawned.elapsed().as_millis() as u64)
+                }
+                Ok(PushEvent::Progress(progress)) => {
+                    metrics.record(&progress, previous.as_ref());
+                    previous = Some(progress);
+                }
+                _ => {}
+            }
+        }
+
+        let status = handle
+            .stop()
+            .unwrap_or_else(|e| PushStatus::Failed(format!("{:?}", e)));
+        // Pick up whatever was reported while stopping
+        for event in handle.events().try_iter() {
+            match event {
+                PushEvent::Connected if metrics.connect_time_ms.is_none() => {
+                    metrics.connect_time_ms = Some(spawned.elapsed().as_millis() as u64)
+                }
+                PushEvent::Progress(progress) => {
+                    metrics.record(&progress, previous.as_ref());
+                    previous = Some(progress);
+                }
+                _ => {}
+            }
+        }
+        metrics.finish(status);
+    }
+}
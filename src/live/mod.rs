@@ -1,5 +1,15 @@
+pub mod handle;
+pub mod http;
+pub mod load;
+pub mod options;
+pub mod pattern;
+pub mod playlist;
+pub(crate) mod reader;
 pub mod rtmp;
+pub mod srt;
 pub mod stream;
+pub mod supervisor;
+pub mod whip;
 
 pub trait Stream {
     fn list_streams(&self) -> Result<crate::data::stream::Streams, crate::errors::Error>;
@@ -20,6 +30,7 @@ pub trait Stream {
         &self,
         name: &String,
         profiles: &Vec<crate::data::stream::Profile>,
-        playback_policy: Option<serde_json::Value>,
+        playback_policy: Option<crate::data::stream::PlaybackPolicy>,
     ) -> Result<String, crate::errors::Error>;
+    fn delete_stream(&self, stream_id: String) -> Result<(), crate::errors::Error>;
 }
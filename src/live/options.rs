@@ -0,0 +1,513 @@
+use crate::errors;
+
+/// Largest frame Livepeer ingest accepts
+pub const MAX_WIDTH: u32 = 3840;
+pub const MAX_HEIGHT: u32 = 2160;
+pub const MAX_FPS: u32 = 60;
+/// Livepeer segments on keyframes and recommends one every 2s, longer intervals delay playback
+pub const MAX_KEYFRAME_INTERVAL: f64 = 4.0;
+pub const MAX_VIDEO_BITRATE: usize = 20_000_000;
+pub const MAX_AUDIO_BITRATE: usize = 320_000;
+
+const X264_PRESETS: &[&str] = &[
+    "ultrafast",
+    "superfast",
+    "veryfast",
+    "faster",
+    "fast",
+    "medium",
+    "slow",
+    "slower",
+    "veryslow",
+];
+const X264_TUNES: &[&str] = &[
+    "film",
+    "animation",
+    "grain",
+    "stillimage",
+    "fastdecode",
+    "zerolatency",
+];
+const X264_PROFILES: &[&str] = &["baseline", "main", "high"];
+/// Encoder options set from `PushOptions` fields, which `x264_opts` can't override
+const X264_RESERVED_OPTS: &[&str] = &[
+    "preset",
+    "tune",
+    "profile",
+    "b",
+    "maxrate",
+    "bufsize",
+    "g",
+    "keyint_min",
+    "x264opts",
+];
+/// `x264-params` settings for the bitrate and keyframes, set from `PushOptions` fields
+pub(crate) const X264_RESERVED_PARAMS: &[&str] = &[
+    "bitrate",
+    "vbv-maxrate",
+    "vbv-bufsize",
+    "keyint",
+    "min-keyint",
+    "scenecut",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AudioCodec {
+    /// Encode to AAC, which is what Livepeer ingest expects. AAC sources are copied as is
+    Aac,
+    /// Pass the source audio through untouched
+    Copy,
+}
+
+/// Encoder settings for pushing to Livepeer ingest
+#[derive(Debug, Clone, PartialEq)]
+pub struct PushOptions {
+    /// Output width, the source width when `None`. Set alone, the height follows the aspect ratio
+    pub width: Option<u32>,
+    /// Output height, the source height when `None`. Set alone, the width follows the aspect ratio
+    pub height: Option<u32>,
+    /// Output frame rate, the source frame rate when `None`
+    pub fps: Option<u32>,
+    /// Target video bitrate in bits per second
+    pub video_bitrate: usize,
+    /// Peak video bitrate, `video_bitrate` when `None`
+    pub max_bitrate: Option<usize>,
+    /// Rate control buffer size in bits, twice `max_bitrate` when `None`
+    pub buffer_size: Option<usize>,
+    /// Seconds between keyframes
+    pub keyframe_interval: f64,
+    pub preset: String,
+    pub tune: Option<String>,
+    pub profile: Option<String>,
+    /// Extra comma-delimited `key=value` encoder options
+    pub x264_opts: Option<String>,
+    pub audio_codec: AudioCodec,
+    /// Audio bitrate in bits per second, when encoding
+    pub audio_bitrate: usize,
+    /// Print encoding progress to stderr about once a second
+    pub log_progress: bool,
+}
+
+impl Default for PushOptions {
+    fn default() -> Self {
+        PushOptions {
+            width: None,
+            height: None,
+            fps: None,
+            video_bitrate: 4_000_000,
+            max_bitrate: None,
+            buffer_size: None,
+            keyframe_interval: 2.0,
+            preset: String::from("veryfast"),
+            tune: Some(String::from("zerolatency")),
+            profile: Some(String::from("main")),
+            x264_opts: None,
+            audio_codec: AudioCodec::Aac,
+            audio_bitrate: 128_000,
+            log_progress: false,
+        }
+    }
+}
+
+impl PushOptions {
+    pub fn with_resolution(mut self, width: u32, height: u32) -> Self {
+        self.width = Some(width);
+        self.height = Some(height);
+        self
+    }
+
+    pub fn with_fps(mut self, fps: u32) -> Self {
+        self.fps = Some(fps);
+        self
+    }
+
+    pub fn with_bitrate(mut self, bitrate: usize) -> Self {
+        self.video_bitrate = bitrate;
+        self
+    }
+
+    pub fn with_keyframe_interval(mut self, seconds: f64) -> Self {
+        self.keyframe_interval = seconds;
+        self
+    }
+
+    pub fn with_preset(mut self, preset: &str) -> Self {
+        self.preset = preset.to_string();
+        self
+    }
+
+    pub fn with_progress_log(mut self, enabled: bool) -> Self {
+        self.log_progress = enabled;
+        self
+    }
+
+    /// Check the options against Livepeer's ingest requirements
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result or a `PUSHOPTIONS` error describing the first problem
+    pub fn validate(&self) -> Result<(), errors::Error> {
+        let invalid = |reason: String| Err(errors::Error::PUSHOPTIONS(reason));
+
+        if let Some(width) = self.width {
+            if width == 0 || width > MAX_WIDTH || width % 2 != 0 {
+                return invalid(format!(
+                    "width must be even and between 2 and {}, got {}",
+                    MAX_WIDTH, width
+                ));
+            }
+        }
+        if let Some(height) = self.height {
+            if height == 0 || height > MAX_HEIGHT || height % 2 != 0 {
+                return invalid(format!(
+                    "height must be even and between 2 and {}, got {}",
+                    MAX_HEIGHT, height
+                ));
+            }
+        }
+        if let Some(fps) = self.fps {
+            if fps == 0 || fps > MAX_FPS {
+                return invalid(format!("fps must be between 1 and {}, got {}", MAX_FPS, fps));
+            }
+        }
+        if self.video_bitrate == 0 || self.video_bitrate > MAX_VIDEO_BITRATE {
+            return invalid(format!(
+                "video bitrate must be between 1 and {}, got {}",
+                MAX_VIDEO_BITRATE, self.video_bitrate
+            ));
+        }
+        if self.max_bitrate() < self.video_bitrate {
+            return invalid(String::from("max bitrate must not be below the video bitrate"));
+        }
+        if self.buffer_size() == 0 {
+            return invalid(String::from("buffer size must not be zero"));
+        }
+        if !(self.keyframe_interval > 0.0 && self.keyframe_interval <= MAX_KEYFRAME_INTERVAL) {
+            return invalid(format!(
+                "keyframe interval must be above 0 and at most {}s, got {}",
+                MAX_KEYFRAME_INTERVAL, self.keyframe_interval
+            ));
+        }
+        if !X264_PRESETS.contains(&self.preset.as_str()) {
+            return invalid(format!("unknown x264 preset {}", self.preset));
+        }
+        if let Some(tune) = &self.tune {
+            if !X264_TUNES.contains(&tune.as_str()) {
+                return invalid(format!("unknown x264 tune {}", tune));
+            }
+        }
+        if let Some(profile) = &self.profile {
+            if !X264_PROFILES.contains(&profile.as_str()) {
+                return invalid(format!("unsupported H.264 profile {}", profile));
+            }
+        }
+        if let Some(opts) = &self.x264_opts {
+            for kv in opts.split_terminator(',') {
+                match kv.split_once('=') {
+                    None => {
+                        return invalid(format!(
+                            "x264 options must be key=value pairs, got {}",
+                            opts
+                        ))
+                    }
+                    // These have their own fields, which would silently win
+                    Some((key, _)) if X264_RESERVED_OPTS.contains(&key) => {
+                        return invalid(format!(
+                            "set {} through its PushOptions field, not the x264 options",
+                            key
+                        ))
+                    }
+                    Some(("x264-params", params)) => {
+                        if let Some((key, _)) = x264_params(params)
+                            .into_iter()
+                            .find(|(key, _)| X264_RESERVED_PARAMS.contains(&key.as_str()))
+                        {
+                            return invalid(format!(
+                                "set {} through its PushOptions field, not the x264-params",
+                                key
+                            ));
+                        }
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+        if self.audio_codec == AudioCodec::Aac
+            && (self.audio_bitrate == 0 || self.audio_bitrate > MAX_AUDIO_BITRATE)
+        {
+            return invalid(format!(
+                "audio bitrate must be between 1 and {}, got {}",
+                MAX_AUDIO_BITRATE, self.audio_bitrate
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check the output size for a source against Livepeer's ingest limits
+    ///
+    /// A single side is scaled by the source aspect ratio, so the other side can still end up
+    /// too large, e.g. a width of 3840 on a portrait source.
+    ///
+    /// # Parameters
+    /// * `source_width` - The source width
+    /// * `source_height` - The source height
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result or a `PUSHOPTIONS` error
+    pub fn validate_output_size(
+        &self,
+        source_width: u32,
+        source_height: u32,
+    ) -> Result<(), errors::Error> {
+        let (width, height) = self.output_size(source_width, source_height);
+        if width > MAX_WIDTH || height > MAX_HEIGHT {
+            return Err(errors::Error::PUSHOPTIONS(format!(
+                "output size {}x{} for a {}x{} source is above {}x{}",
+                width, height, source_width, source_height, MAX_WIDTH, MAX_HEIGHT
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn max_bitrate(&self) -> usize {
+        self.max_bitrate.unwrap_or(self.video_bitrate)
+    }
+
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size.unwrap_or(self.max_bitrate() * 2)
+    }
+
+    /// Output size for a source size, keeping the aspect ratio when only one side is set
+    pub fn output_size(&self, source_width: u32, source_height: u32) -> (u32, u32) {
+        let even = |v: f64| ((v / 2.0).round() as u32 * 2).max(2);
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => (width, height),
+            (Some(width), None) if source_width > 0 => (
+                width,
+                even(source_height as f64 * width as f64 / source_width as f64),
+            ),
+            (None, Some(height)) if source_height > 0 => (
+                even(source_width as f64 * height as f64 / source_height as f64),
+                height,
+            ),
+            _ => (source_width, source_height),
+        }
+    }
+}
+
+/// Split `x264-params` into its settings, with keys spelled the way x264 reads them
+///
+/// x264 takes `_` for `-` and a `no-` prefix to negate a setting, so `no_scenecut` is `scenecut`.
+pub(crate) fn x264_params(params: &str) -> Vec<(String, Option<&str>)> {
+    params
+        .split(':')
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let (key, value) = match param.split_once('=') {
+                Some((key, value)) => (key, Some(value)),
+                None => (param, None),
+            };
+            let key = key.replace('_', "-");
+            let key = key.strip_prefix("no-").map(str::to_string).unwrap_or(key);
+            (key, value)
+        })
+        .collect()
+}
+
+/// Port of Livepeer's SRT ingest
+pub const SRT_PORT: u16 = 2935;
+/// SRT passphrase length limits, in characters
+const SRT_PASSPHRASE_LENGTH: std::ops::RangeInclusive<usize> = 10..=79;
+/// Characters with a meaning in the SRT URL query, which ffmpeg doesn't unescape
+const SRT_RESERVED: &[char] = &['&', '?', '#', '%', '=', ' '];
+
+/// Connection settings for pushing to Livepeer's SRT ingest
+#[derive(Debug, Clone, PartialEq)]
+pub struct SrtOptions {
+    /// How long the receiver buffers to recover lost packets. Raise it on lossy or distant links,
+    /// a common rule is four times the round trip time
+    pub latency: std::time::Duration,
+    /// Encrypt the connection with this passphrase, of 10 to 79 characters
+    pub passphrase: Option<String>,
+    /// AES key length in bytes for encryption: 16, 24 or 32
+    pub key_length: Option<u32>,
+    pub port: u16,
+}
+
+impl Default for SrtOptions {
+    fn default() -> Self {
+        SrtOptions {
+            latency: std::time::Duration::from_millis(500),
+            passphrase: None,
+            key_length: None,
+            port: SRT_PORT,
+        }
+    }
+}
+
+impl SrtOptions {
+    pub fn with_latency(mut self, latency: std::time::Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    pub fn with_passphrase(mut self, passphrase: &str) -> Self {
+        self.passphrase = Some(passphrase.to_string());
+        self
+    }
+
+    /// Check the settings are accepted by SRT
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result or a `PUSHOPTIONS` error describing the problem
+    pub fn validate(&self) -> Result<(), errors::Error> {
+        let invalid = |reason: String| Err(errors::Error::PUSHOPTIONS(reason));
+
+        if let Some(passphrase) = &self.passphrase {
+            if !SRT_PASSPHRASE_LENGTH.contains(&passphrase.chars().count()) {
+                return invalid(String::from(
+                    "SRT passphrase must be 10 to 79 characters long",
+                ));
+            }
+            if passphrase.contains(SRT_RESERVED) {
+                return invalid(format!(
+                    "SRT passphrase must not contain any of {:?}",
+                    SRT_RESERVED
+                ));
+            }
+        }
+        if let Some(key_length) = self.key_length {
+            if ![16, 24, 32].contains(&key_length) {
+                return invalid(format!(
+                    "SRT key length must be 16, 24 or 32, got {}",
+                    key_length
+                ));
+            }
+            if self.passphrase.is_none() {
+                return invalid(String::from("SRT key length needs a passphrase"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A STUN or TURN server for WebRTC connectivity
+#[derive(Debug, Clone, PartialEq)]
+pub struct IceServer {
+    /// e.g. `stun:stun.l.google.com:19302` or `turn:turn.example.com:3478`
+    pub urls: Vec<String>,
+    pub username: Option<String>,
+    pub credential: Option<String>,
+}
+
+impl IceServer {
+    pub fn stun(url: &str) -> Self {
+        IceServer {
+            urls: vec![url.to_string()],
+            username: None,
+            credential: None,
+        }
+    }
+}
+
+/// Connection settings for publishing over WebRTC with WHIP
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhipOptions {
+    /// Servers used to gather candidates, only host candidates are offered when empty
+    pub ice_servers: Vec<IceServer>,
+    /// Offer an Opus audio track next to the H.264 video
+    pub audio: bool,
+    /// How long the offer/answer exchange and ICE may take before giving up
+    pub connect_timeout: std::time::Duration,
+}
+
+impl Default for WhipOptions {
+    fn default() -> Self {
+        WhipOptions {
+            ice_servers: vec![IceServer::stun("stun:stun.l.google.com:19302")],
+            audio: true,
+            connect_timeout: std::time::Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HttpMethod {
+    Put,
+    Post,
+}
+
+/// Settings for pushing MPEG-TS segments to Livepeer's HTTP ingest
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpPushOptions {
+    pub method: HttpMethod,
+    /// Target segment length in seconds. Segments are cut on keyframes, so it should be a
+    /// multiple of the keyframe interval
+    pub segment_duration: f64,
+    /// Retries of a segment after a network or server error, before moving on to the next one
+    pub retries: u32,
+    pub backoff: crate::live::supervisor::Backoff,
+    /// Timeout of each upload, which includes transcoding when renditions are requested
+    pub timeout: std::time::Duration,
+    /// Ask for the transcoded renditions in the response, as `multipart/mixed`
+    pub renditions: bool,
+    /// Base URL segments are sent to, `<host>/live` when `None`
+    pub ingest: Option<String>,
+}
+
+impl Default for HttpPushOptions {
+    fn default() -> Self {
+        HttpPushOptions {
+            method: HttpMethod::Put,
+            segment_duration: 2.0,
+            retries: 3,
+            backoff: crate::live::supervisor::Backoff {
+                initial: std::time::Duration::from_millis(250),
+                max: std::time::Duration::from_secs(2),
+                multiplier: 2.0,
+            },
+            timeout: std::time::Duration::from_secs(10),
+            renditions: false,
+            ingest: None,
+        }
+    }
+}
+
+impl HttpPushOptions {
+    pub fn with_method(mut self, method: HttpMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    pub fn with_renditions(mut self, renditions: bool) -> Self {
+        self.renditions = renditions;
+        self
+    }
+
+    pub fn with_ingest(mut self, ingest: &str) -> Self {
+        self.ingest = Some(ingest.to_string());
+        self
+    }
+
+    /// Check the settings work with the encoder settings
+    ///
+    /// # Parameters
+    /// * `options` - The encoder settings the segments are made with
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result or a `PUSHOPTIONS` error describing the problem
+    pub fn validate(&self, options: &PushOptions) -> Result<(), errors::Error> {
+        if !(self.segment_duration > 0.0 && self.segment_duration <= 10.0) {
+            return Err(errors::Error::PUSHOPTIONS(format!(
+                "segment duration must be between 0 and 10s, got {}",
+                self.segment_duration
+            )));
+        }
+        if self.segment_duration < options.keyframe_interval {
+            return Err(errors::Error::PUSHOPTIONS(format!(
+                "segment duration {}s is shorter than the keyframe interval {}s",
+                self.segment_duration, options.keyframe_interval
+            )));
+        }
+        Ok(())
+    }
+}
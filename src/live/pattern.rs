@@ -0,0 +1,270 @@
+use crate::errors;
+use std::time::Duration;
+
+/// 5x7 glyphs for the timecode, one byte per row with the leftmost pixel in bit 4
+const DIGITS: [[u8; 7]; 10] = [
+    [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+    [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+    [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+    [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+    [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+    [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+    [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+    [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+    [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+    [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+];
+const COLON: [u8; 7] = [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00];
+
+/// 75% SMPTE bars: white, yellow, cyan, green, magenta, red, blue
+const BARS: [(u8, u8, u8); 7] = [
+    (191, 191, 191),
+    (191, 191, 0),
+    (0, 191, 191),
+    (0, 191, 0),
+    (191, 0, 191),
+    (191, 0, 0),
+    (0, 0, 191),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatternKind {
+    /// Color bars with a box sweeping across them
+    ColorBars,
+    /// A scrolling checkerboard, which is much harder to compress
+    Checkerboard,
+}
+
+/// A synthetic source of video and a sine tone, for pushing without media files
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestPattern {
+    pub kind: PatternKind,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    /// How long to generate for, until stopped when `None`
+    pub duration: Option<Duration>,
+    /// Burn the `HH:MM:SS:FF` timecode into the picture
+    pub timecode: bool,
+    /// Tone frequency in Hz
+    pub tone_frequency: f64,
+    pub sample_rate: u32,
+}
+
+impl Default for TestPattern {
+    fn default() -> Self {
+        TestPattern {
+            kind: PatternKind::ColorBars,
+            width: 1280,
+            height: 720,
+            fps: 30,
+            duration: Some(Duration::from_secs(60)),
+            timecode: true,
+            tone_frequency: 1000.0,
+            sample_rate: 48000,
+        }
+    }
+}
+
+/// A planar YUV 4:2:0 picture
+#[derive(Debug, Clone, PartialEq)]
+pub struct YuvImage {
+    pub width: u32,
+    pub height: u32,
+    pub y: Vec<u8>,
+    pub u: Vec<u8>,
+    pub v: Vec<u8>,
+}
+
+impl YuvImage {
+    fn new(width: u32, height: u32) -> Self {
+        let chroma = (width.div_ceil(2) * height.div_ceil(2)) as usize;
+        YuvImage {
+            width,
+            height,
+            y: vec![16; (width * height) as usize],
+            u: vec![128; chroma],
+            v: vec![128; chroma],
+        }
+    }
+
+    /// Fill a rectangle, clipped to the picture
+    fn fill(&mut self, x: u32, y: u32, width: u32, height: u32, color: (u8, u8, u8)) {
+        let x1 = (x + width).min(self.width);
+        let y1 = (y + height).min(self.height);
+        let chroma_width = self.width.div_ceil(2);
+        for row in y..y1 {
+            for col in x..x1 {
+                self.y[(row * self.width + col) as usize] = color.0;
+                if row % 2 == 0 && col % 2 == 0 {
+                    let i = ((row / 2) * chroma_width + col / 2) as usize;
+                    self.u[i] = color.1;
+                    self.v[i] = color.2;
+                }
+            }
+        }
+    }
+
+    /// Luma of a pixel
+    pub fn luma(&self, x: u32, y: u32) -> u8 {
+        self.y[(y * self.width + x) as usize]
+    }
+}
+
+impl TestPattern {
+    pub fn new(kind: PatternKind, width: u32, height: u32, fps: u32) -> Self {
+        TestPattern {
+            kind,
+            width,
+            height,
+            fps,
+            ..TestPattern::default()
+        }
+    }
+
+    pub fn with_duration(mut self, duration: Option<Duration>) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Check the pattern can be encoded
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result or a `PUSHOPTIONS` error describing the problem
+    pub fn validate(&self) -> Result<(), errors::Error> {
+        if self.width < 2 || self.height < 2 || self.width % 2 != 0 || self.height % 2 != 0 {
+            return Err(errors::Error::PUSHOPTIONS(format!(
+                "pattern size must be even, got {}x{}",
+                self.width, self.height
+            )));
+        }
+        if self.fps == 0 || self.sample_rate == 0 {
+            return Err(errors::Error::PUSHOPTIONS(String::from(
+                "pattern fps and sample rate must not be zero",
+            )));
+        }
+        Ok(())
+    }
+
+    /// Number of video frames to generate, `None` when running until stopped
+    pub fn frame_count(&self) -> Option<u64> {
+        self.duration
+            .map(|duration| (duration.as_secs_f64() * self.fps as f64).round() as u64)
+    }
+
+    /// The `HH:MM:SS:FF` timecode of a frame
+    pub fn timecode(&self, index: u64) -> String {
+        let fps = self.fps.max(1) as u64;
+        let seconds = index / fps;
+        format!(
+            "{:02}:{:02}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+            index % fps
+        )
+    }
+
+    /// Draw a frame
+    ///
+    /// # Parameters
+    /// * `index` - The frame number, counting from 0
+    ///
+    /// # Returns
+    /// * `YuvImage` - The picture, in BT.601 limited range
+    pub fn render(&self, index: u64) -> YuvImage {
+        let mut image = YuvImage::new(self.width, self.height);
+        match self.kind {
+            PatternKind::ColorBars => {
+                let bar_width = self.width.div_ceil(BARS.len() as u32);
+                for (i, (r, g, b)) in BARS.iter().enumerate() {
+                    image.fill(
+                        i as u32 * bar_width,
+                        0,
+                        bar_width,
+                        self.height,
+                        rgb_to_yuv(*r, *g, *b),
+                    );
+                }
+                // A sweeping box shows at a glance that the stream is live
+                let size = (self.height / 8).max(2);
+                let travel = self.width.saturating_sub(size).max(1) as u64;
+                let x = (index * 8 % (travel * 2)) as u32;
+                let x = if x as u64 > travel { (travel * 2) as u32 - x } else { x };
+                image.fill(x, self.height / 8, size, size, rgb_to_yuv(255, 255, 255));
+            }
+            PatternKind::Checkerboard => {
+                let size = (self.height / 9).max(2);
+                let offset = (index * 4 % (size as u64 * 2)) as u32;
+                for row in 0..self.height {
+                    for col in 0..self.width {
+                        let dark = ((col + offset) / size + row / size) % 2 == 0;
+                        image.y[(row * self.width + col) as usize] = if dark { 16 } else { 235 };
+                    }
+                }
+            }
+        }
+        if self.timecode {
+            self.draw_timecode(&mut image, index);
+        }
+        image
+    }
+
+    /// Draw the timecode on a black box in the lower third
+    fn draw_timecode(&self, image: &mut YuvImage, index: u64) {
+        let text = self.timecode(index);
+        let scale = (self.height / 90).max(1);
+        let advance = 6 * scale;
+        let text_width = advance * text.len() as u32;
+        let x = self.width.saturating_sub(text_width) / 2;
+        let y = self.height * 2 / 3;
+        image.fill(
+            x.saturating_sub(2 * scale),
+            y.saturating_sub(2 * scale),
+            text_width + 3 * scale,
+            11 * scale,
+            (16, 128, 128),
+        );
+        for (i, c) in text.chars().enumerate() {
+            let glyph = match c.to_digit(10) {
+                Some(d) => &DIGITS[d as usize],
+                None => &COLON,
+            };
+            let left = x + i as u32 * advance;
+            for (row, bits) in glyph.iter().enumerate() {
+                for col in 0..5 {
+                    if bits & (0x10 >> col) != 0 {
+                        image.fill(
+                            left + col * scale,
+                            y + row as u32 * scale,
+                            scale,
+                            scale,
+                            (235, 128, 128),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Samples of the tone, at -12dBFS
+    ///
+    /// # Parameters
+    /// * `start` - The index of the first sample
+    /// * `count` - How many samples to generate
+    pub fn tone(&self, start: u64, count: usize) -> Vec<f32> {
+        let step = 2.0 * std::f64::consts::PI * self.tone_frequency / self.sample_rate as f64;
+        (0..count as u64)
+            .map(|i| (0.25 * ((start + i) as f64 * step).sin()) as f32)
+            .collect()
+    }
+}
+
+/// Convert 8-bit RGB to BT.601 limited range YUV
+fn rgb_to_yuv(r: u8, g: u8, b: u8) -> (u8, u8, u8) {
+    let (r, g, b) = (r as f64, g as f64, b as f64);
+    let y = 16.0 + (65.738 * r + 129.057 * g + 25.064 * b) / 256.0;
+    let u = 128.0 + (-37.945 * r - 74.494 * g + 112.439 * b) / 256.0;
+    let v = 128.0 + (112.439 * r - 94.154 * g - 18.285 * b) / 256.0;
+    (y.round() as u8, u.round() as u8, v.round() as u8)
+}
@@ -0,0 +1,352 @@
+use crate::errors;
+use crate::live::handle::{ProgressMeter, PushControl, PushEvent};
+use crate::live::options::PushOptions;
+use crate::live::rtmp::{source_frame_rate, AudioTranscoder, Pacer, Transcoder, VideoSource};
+use ffmpeg_next::{format, media, ChannelLayout, Rational};
+use rand::seq::SliceRandom;
+use std::time::SystemTime;
+
+/// A file in a playlist
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaylistItem {
+    pub path: String,
+    /// Wall-clock time the item must start at
+    ///
+    /// The item before it is cut short at that time, or the channel shows black and silence
+    /// until then if the playlist gets there early. A time the channel is already past, such as
+    /// on later passes of a looped playlist, is ignored and the item plays in turn.
+    pub start_at: Option<SystemTime>,
+}
+
+impl PlaylistItem {
+    pub fn new(path: &str) -> Self {
+        PlaylistItem {
+            path: path.to_string(),
+            start_at: None,
+        }
+    }
+
+    pub fn at(path: &str, start_at: SystemTime) -> Self {
+        PlaylistItem {
+            path: path.to_string(),
+            start_at: Some(start_at),
+        }
+    }
+}
+
+/// Files pushed back to back over one connection, for an always-on channel
+#[derive(Debug, Clone, PartialEq)]
+pub struct Playlist {
+    pub items: Vec<PlaylistItem>,
+    /// Start over after the last item, until stopped
+    pub looped: bool,
+    /// Play the unscheduled items in a new random order on every pass
+    pub shuffle: bool,
+}
+
+impl Playlist {
+    pub fn new(paths: &[&str]) -> Self {
+        Playlist {
+            items: paths.iter().map(|path| PlaylistItem::new(path)).collect(),
+            looped: false,
+            shuffle: false,
+        }
+    }
+
+    pub fn with_loop(mut self, looped: bool) -> Self {
+        self.looped = looped;
+        self
+    }
+
+    pub fn with_shuffle(mut self, shuffle: bool) -> Self {
+        self.shuffle = shuffle;
+        self
+    }
+
+    pub fn push(mut self, item: PlaylistItem) -> Self {
+        self.items.push(item);
+        self
+    }
+
+    /// Check the playlist can be played
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result or a `PUSHOPTIONS` error describing the problem
+    pub fn validate(&self) -> Result<(), errors::Error> {
+        if self.items.is_empty() {
+            return Err(errors::Error::PUSHOPTIONS(String::from("playlist is empty")));
+        }
+        let scheduled: Vec<SystemTime> = self.items.iter().filter_map(|item| item.start_at).collect();
+        if scheduled.windows(2).any(|pair| pair[1] <= pair[0]) {
+            return Err(errors::Error::PUSHOPTIONS(String::from(
+                "scheduled start times must increase through the playlist",
+            )));
+        }
+        Ok(())
+    }
+
+    /// The order to play items in for one pass
+    ///
+    /// Scheduled items keep their place, shuffling only moves the items between them.
+    ///
+    /// # Returns
+    /// * `Vec<usize>` - Indexes into `items`
+    pub fn order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.items.len()).collect();
+        if self.shuffle {
+            let slots: Vec<usize> = order
+                .iter()
+                .copied()
+                .filter(|&i| self.items[i].start_at.is_none())
+                .collect();
+            let mut shuffled = slots.clone();
+            shuffled.shuffle(&mut rand::thread_rng());
+            for (slot, index) in slots.into_iter().zip(shuffled) {
+                order[slot] = index;
+            }
+        }
+        order
+    }
+}
+
+/// Where a scheduled item goes on the timeline of a push, in seconds from its start
+///
+/// # Parameters
+/// * `item` - The item
+/// * `started` - When the push started
+/// * `now` - How far the timeline has got, in seconds
+///
+/// # Returns
+/// * `Option<f64>` - The offset, or `None` when the item isn't scheduled or its time is no longer ahead
+pub(crate) fn schedule_offset(item: &PlaylistItem, started: SystemTime, now: f64) -> Option<f64> {
+    item.start_at
+        .and_then(|at| at.duration_since(started).ok())
+        .map(|offset| offset.as_secs_f64())
+        .filter(|&offset| offset > now)
+}
+
+/// The video of a file, to size the output of a playlist
+fn probe_video(file_path: &str) -> Result<VideoSource, errors::Error> {
+    let ictx = format::input(&file_path)?;
+    let ist = ictx
+        .streams()
+        .best(media::Type::Video)
+        .ok_or(ffmpeg_next::Error::StreamNotFound)?;
+    let decoder = ffmpeg_next::codec::context::Context::from_parameters(ist.parameters())?
+        .decoder()
+        .video()?;
+    Ok(VideoSource {
+        width: decoder.width(),
+        height: decoder.height(),
+        format: decoder.format(),
+        aspect_ratio: decoder.aspect_ratio(),
+        frame_rate: source_frame_rate(&ist),
+    })
+}
+
+/// When a stream's first packet is due, in seconds
+fn stream_start(stream: &format::stream::Stream) -> f64 {
+    match stream.start_time() {
+        ffmpeg_next::ffi::AV_NOPTS_VALUE => 0.0,
+        start => start as f64 * f64::from(stream.time_base()),
+    }
+}
+
+/// Send black frames and silence in realtime until `until` seconds
+fn fill_gap(
+    video: &mut Transcoder,
+    audio: &mut AudioTranscoder,
+    until: f64,
+    octx: &mut format::context::Output,
+    time_bases: (Rational, Rational),
+    pacer: &mut Pacer,
+    control: &PushControl,
+) -> Result<(), errors::Error> {
+    while video.end_time() < until && !control.is_stopped() {
+        let timestamp = video.end_time();
+        pacer.wait(timestamp);
+        video.encode_black(timestamp, octx, time_bases.0)?;
+        audio.fill_silence(video.end_time(), octx, time_bases.1)?;
+    }
+    audio.fill_silence(until, octx, time_bases.1)?;
+    Ok(())
+}
+
+/// Push the files of a playlist one after another over a single connection
+///
+/// Every item is re-encoded onto one timeline, so the ingest sees a single stream with no gaps
+/// in the timestamps. Items that can't be opened or have no video are skipped with a
+/// `PushEvent::ItemFailed`.
+pub(crate) fn push_playlist(
+    playlist: &Playlist,
+    output_url: &String,
+    output_format: &str,
+    options: &PushOptions,
+    control: &PushControl,
+) -> Result<(), errors::Error> {
+    ffmpeg_next::init()?;
+    let first = playlist
+        .items
+        .iter()
+        .find_map(|item| probe_video(&item.path).ok())
+        .ok_or(errors::Error::FFMPEG(String::from(
+            "no item in the playlist has a video stream",
+        )))?;
+    // Items are scaled to one size, so fix it from the first one when not set
+    let source = VideoSource {
+        format: format::Pixel::YUV420P,
+        ..first
+    };
+    let mut octx = format::output_as(output_url, output_format)?;
+    let mut video = Transcoder::from_frames(&source, &mut octx, 0, options)?;
+    // Always re-encoded, so every item lands on the same AAC track
+    let mut audio =
+        AudioTranscoder::from_frames(&mut octx, 1, 48000, ChannelLayout::STEREO, options)?;
+    audio.next_pts = Some(0);
+
+    octx.write_header()?;
+    control.connected();
+    let started = SystemTime::now();
+    let time_bases = (
+        octx.stream(0).unwrap().time_base(),
+        octx.stream(1).unwrap().time_base(),
+    );
+
+    let mut pacer = Pacer::new();
+    pacer.wait(0.0);
+    let mut meter = ProgressMeter::new();
+    'passes: loop {
+        let order = playlist.order();
+        let mut played = 0;
+        for (position, &index) in order.iter().enumerate() {
+            if control.is_stopped() {
+                break 'passes;
+            }
+            let item = &playlist.items[index];
+            // Scheduled items are placed on the timeline by their offset from the start of the push
+            if let Some(start) = schedule_offset(item, started, video.end_time()) {
+                fill_gap(&mut video, &mut audio, start, &mut octx, time_bases, &mut pacer, control)?;
+            }
+            // Cut the item short when the next one is scheduled to start
+            let now = video.end_time().max(audio.end_time());
+            let cut_at = order
+                .get(position + 1)
+                .and_then(|&next| schedule_offset(&playlist.items[next], started, now));
+
+            let mut ictx = match format::input(&item.path) {
+                Ok(ictx) => ictx,
+                Err(e) => {
+                    control.send(PushEvent::ItemFailed {
+                        index,
+                        path: item.path.clone(),
+                        error: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            let offset = video.end_time().max(audio.end_time());
+            let video_index = ictx.streams().best(media::Type::Video).map(|s| s.index());
+            let audio_index = ictx.streams().best(media::Type::Audio).map(|s| s.index());
+            let attached = match video_index.and_then(|i| ictx.stream(i)) {
+                Some(ist) => {
+                    let start = stream_start(&ist);
+                    video.attach(&ist, offset - start).map(|_| start).map_err(errors::Error::from)
+                }
+                None => Err(errors::Error::from(ffmpeg_next::Error::StreamNotFound)),
+            }
+            .and_then(|start| {
+                match audio_index.and_then(|i| ictx.stream(i)) {
+                    Some(ist) => audio.attach(&ist)?,
+                    None => audio.detach(),
+                }
+                Ok(start)
+            });
+            let start = match attached {
+                Ok(start) => start,
+                Err(e) => {
+                    video.detach();
+                    let error = match e {
+                        errors::Error::FFMPEG(message) => message,
+                        e => format!("{:?}", e),
+                    };
+                    control.send(PushEvent::ItemFailed {
+                        index,
+                        path: item.path.clone(),
+                        error,
+                    });
+                    continue;
+                }
+            };
+            played += 1;
+            control.send(PushEvent::ItemStarted {
+                index,
+                path: item.path.clone(),
+            });
+
+            let mut cut = false;
+            for (stream, packet) in ictx.packets() {
+                if control.is_stopped() {
+                    break 'passes;
+                }
+                let is_video = Some(stream.index()) == video_index;
+                if !is_video && Some(stream.index()) != audio_index {
+                    continue;
+                }
+                if let Some(ts) = packet.dts().or(packet.pts()) {
+                    let timestamp = ts as f64 * f64::from(stream.time_base()) - start + offset;
+                    if cut_at.map_or(false, |cut_at| timestamp >= cut_at) {
+                        cut = true;
+                        break;
+                    }
+                    pacer.wait(timestamp);
+                }
+                if is_video {
+                    video.send_packet_to_decoder(&packet)?;
+                    video.receive_and_process_decoded_frames(&mut octx, time_bases.0)?;
+                    if audio_index.is_none() {
+                        // Keep audio level with the video, or the muxer holds the video back
+                        audio.fill_silence(video.end_time(), &mut octx, time_bases.1)?;
+                    }
+                } else {
+                    audio.send_packet_to_decoder(&packet)?;
+                    audio.receive_and_process_decoded_frames(&mut octx, time_bases.1)?;
+                }
+                if meter.is_due() {
+                    control.report(meter.measure(
+                        video.encoded_frames,
+                        video.dropped_frames,
+                        video.encoded_bytes + audio.encoded_bytes,
+                        video.end_time(),
+                    ));
+                }
+            }
+            if !cut {
+                // Drain the decoders but not the audio filter, a partial frame would end the AAC track
+                video.send_eof_to_decoder()?;
+                video.receive_and_process_decoded_frames(&mut octx, time_bases.0)?;
+                audio.send_eof_to_decoder()?;
+                audio.receive_and_process_decoded_frames(&mut octx, time_bases.1)?;
+            }
+            video.detach();
+            audio.detach();
+            // Line both tracks up for the next item
+            let end = video.end_time().max(audio.end_time());
+            fill_gap(&mut video, &mut audio, end, &mut octx, time_bases, &mut pacer, control)?;
+        }
+        if played == 0 {
+            return Err(errors::Error::FFMPEG(String::from(
+                "no item in the playlist could be played",
+            )));
+        }
+        if !playlist.looped {
+            break;
+        }
+    }
+
+    video.send_eof_to_encoder()?;
+    video.receive_and_process_encoded_packets(&mut octx, time_bases.0)?;
+    audio.send_eof_to_encoder()?;
+    audio.receive_and_process_encoded_packets(&mut octx, time_bases.1)?;
+    octx.write_trailer()?;
+    Ok(())
+}
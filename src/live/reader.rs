@@ -0,0 +1,149 @@
+use std::ffi::CString;
+use std::io::Read;
+use std::ops::{Deref, DerefMut};
+use std::os::raw::{c_int, c_void};
+use std::ptr;
+
+use ffmpeg_next::{ffi, format};
+
+/// Size of the buffer ffmpeg reads through, as recommended for `avio_alloc_context`
+const BUFFER_SIZE: usize = 32 * 1024;
+
+type BoxedReader = Box<dyn Read + Send>;
+
+/// A demuxer reading from any `Read` rather than a file, like stdin, a pipe or a buffer
+///
+/// The source is read front to back and can't seek, so it must be a streamable container such as
+/// MPEG-TS or FLV.
+pub(crate) struct ReaderInput {
+    input: Option<format::context::Input>,
+    avio: *mut ffi::AVIOContext,
+    reader: *mut BoxedReader,
+}
+
+// The reader is `Send` and only ever touched through the context that owns it
+unsafe impl Send for ReaderInput {}
+
+impl ReaderInput {
+    /// Open a reader and probe its streams
+    ///
+    /// # Parameters
+    /// * `reader` - The source
+    /// * `format_name` - The container, e.g. `mpegts` or `flv`, probed from the data when `None`
+    ///
+    /// # Returns
+    /// * `Result<ReaderInput, ffmpeg_next::Error>` - The opened input, or the demuxer error
+    pub(crate) fn open<R: Read + Send + 'static>(
+        reader: R,
+        format_name: Option<&str>,
+    ) -> Result<ReaderInput, ffmpeg_next::Error> {
+        ffmpeg_next::init()?;
+        let format_name = match format_name {
+            Some(name) => Some(CString::new(name).map_err(|_| ffmpeg_next::Error::InvalidData)?),
+            None => None,
+        };
+
+        unsafe {
+            let buffer = ffi::av_malloc(BUFFER_SIZE) as *mut u8;
+            if buffer.is_null() {
+                return Err(ffmpeg_next::Error::Unknown);
+            }
+            let reader: *mut BoxedReader = Box::into_raw(Box::new(Box::new(reader)));
+            let avio = ffi::avio_alloc_context(
+                buffer,
+                BUFFER_SIZE as c_int,
+                0,
+                reader as *mut c_void,
+                Some(read_packet),
+                None,
+                None,
+            );
+            // From here on dropping `opened` frees the buffer, the AVIO context and the reader
+            let mut opened = ReaderInput {
+                input: None,
+                avio,
+                reader,
+            };
+            if avio.is_null() {
+                ffi::av_free(buffer as *mut c_void);
+                return Err(ffmpeg_next::Error::Unknown);
+            }
+
+            let mut ctx = ffi::avformat_alloc_context();
+            if ctx.is_null() {
+                return Err(ffmpeg_next::Error::Unknown);
+            }
+            (*ctx).pb = avio;
+            let ret = match &format_name {
+                Some(name) => {
+                    let input_format = ffi::av_find_input_format(name.as_ptr());
+                    if input_format.is_null() {
+                        ffi::avformat_free_context(ctx);
+                        return Err(ffmpeg_next::Error::DemuxerNotFound);
+                    }
+                    ffi::avformat_open_input(&mut ctx, ptr::null(), input_format, ptr::null_mut())
+                }
+                None => ffi::avformat_open_input(
+                    &mut ctx,
+                    ptr::null(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                ),
+            };
+            // `avformat_open_input` frees the context when it fails
+            if ret < 0 {
+                return Err(ffmpeg_next::Error::from(ret));
+            }
+
+            let input = format::context::Input::wrap(ctx);
+            let ret = ffi::avformat_find_stream_info(ctx, ptr::null_mut());
+            opened.input = Some(input);
+            if ret < 0 {
+                return Err(ffmpeg_next::Error::from(ret));
+            }
+            Ok(opened)
+        }
+    }
+}
+
+impl Deref for ReaderInput {
+    type Target = format::context::Input;
+
+    fn deref(&self) -> &Self::Target {
+        self.input.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for ReaderInput {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.input.as_mut().unwrap()
+    }
+}
+
+impl Drop for ReaderInput {
+    fn drop(&mut self) {
+        // Close the demuxer first, it leaves a custom AVIO context to its owner
+        self.input.take();
+        unsafe {
+            if !self.avio.is_null() {
+                ffi::av_freep(&mut (*self.avio).buffer as *mut *mut u8 as *mut c_void);
+                ffi::avio_context_free(&mut self.avio);
+            }
+            drop(Box::from_raw(self.reader));
+        }
+    }
+}
+
+/// AVIO read callback, filling `buf` from the boxed reader behind `opaque`
+unsafe extern "C" fn read_packet(opaque: *mut c_void, buf: *mut u8, buf_size: c_int) -> c_int {
+    let reader = &mut *(opaque as *mut BoxedReader);
+    let buf = std::slice::from_raw_parts_mut(buf, buf_size.max(0) as usize);
+    loop {
+        match reader.read(buf) {
+            Ok(0) => return ffi::AVERROR_EOF,
+            Ok(read) => return read as c_int,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(_) => return ffi::AVERROR_EXTERNAL,
+        }
+    }
+}
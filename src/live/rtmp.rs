@@ -3,20 +3,41 @@ pub struct Rtmp {
     pub client: crate::LivepeerClient,
 }
 
+use crate::errors;
 use std::collections::HashMap;
 use std::env;
-use std::time::Instant;
+use std::io::Read;
+use std::time::{Duration, Instant};
 
+use ffmpeg_next::software::scaling;
 use ffmpeg_next::{
-    codec, decoder, encoder, format, frame, log, media, picture, Dictionary, Packet, Rational,
+    codec, decoder, encoder, filter, format, frame, log, media, picture, ChannelLayout,
+    Dictionary, Packet, Rational,
 };
 
-const DEFAULT_X264_OPTS: &str = "preset=medium";
+use crate::live::handle::{ProgressMeter, PushControl, PushHandle, PushStatus};
+use crate::live::options::{x264_params, AudioCodec, PushOptions, X264_RESERVED_PARAMS};
+use crate::live::pattern::TestPattern;
+use crate::live::playlist::{push_playlist, Playlist};
+use crate::live::supervisor::{supervise, FailoverPolicy};
+use crate::live::reader::ReaderInput;
 
-struct Transcoder {
+/// Frame rate used when the source doesn't declare one
+const FALLBACK_FPS: i32 = 30;
+
+pub(crate) struct Transcoder {
     ost_index: usize,
-    decoder: decoder::Video,
-    encoder: encoder::video::Video,
+    decoder: Option<decoder::Video>,
+    input_time_base: Rational,
+    encoder: encoder::Video,
+    frame_rate: Rational,
+    scaler: Option<scaling::Context>,
+    /// Added to decoded timestamps, to place a file on a longer timeline
+    timestamp_offset: f64,
+    last_pts: Option<i64>,
+    pub(crate) encoded_frames: u64,
+    pub(crate) encoded_bytes: u64,
+    pub(crate) dropped_frames: u64,
     logging_enabled: bool,
     frame_count: usize,
     last_log_frame_count: usize,
@@ -24,50 +45,105 @@ struct Transcoder {
     last_log_time: Instant,
 }
 
+/// The frames a `Transcoder` is fed
+pub(crate) struct VideoSource {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) format: format::Pixel,
+    pub(crate) aspect_ratio: Rational,
+    pub(crate) frame_rate: Rational,
+}
+
 impl Transcoder {
     fn new(
         ist: &format::stream::Stream,
         octx: &mut format::context::Output,
         ost_index: usize,
-        x264_opts: Dictionary,
-        enable_logging: bool,
-    ) -> Result<Self, ffmpeg_next::Error> {
-        let global_header = octx.format().flags().contains(format::Flags::GLOBAL_HEADER);
+        options: &PushOptions,
+    ) -> Result<Self, errors::Error> {
         let decoder = ffmpeg_next::codec::context::Context::from_parameters(ist.parameters())?
             .decoder()
             .video()?;
+        let source = VideoSource {
+            width: decoder.width(),
+            height: decoder.height(),
+            format: decoder.format(),
+            aspect_ratio: decoder.aspect_ratio(),
+            frame_rate: source_frame_rate(ist),
+        };
+        let mut transcoder = Self::from_frames(&source, octx, ost_index, options)?;
+        transcoder.decoder = Some(decoder);
+        transcoder.input_time_base = ist.time_base();
+        Ok(transcoder)
+    }
+
+    /// A transcoder fed with raw frames through `encode_frame` rather than packets
+    pub(crate) fn from_frames(
+        source: &VideoSource,
+        octx: &mut format::context::Output,
+        ost_index: usize,
+        options: &PushOptions,
+    ) -> Result<Self, errors::Error> {
+        options.validate_output_size(source.width, source.height)?;
+        let global_header = octx.format().flags().contains(format::Flags::GLOBAL_HEADER);
         let mut ost = octx.add_stream(encoder::find(codec::Id::H264))?;
         let mut encoder = codec::context::Context::from_parameters(ost.parameters())?
             .encoder()
             .video()?;
-        let fps = Some(ffmpeg_next::util::rational::Rational::new(30, 30));
-        let width = decoder.width();
-        println!("FPS {:?}", fps);
-        println!("Width {:?}", width);
-        encoder.set_height(decoder.height());
+
+        let frame_rate = match options.fps {
+            Some(fps) => Rational(fps as i32, 1),
+            None => source.frame_rate,
+        };
+        let (width, height) = options.output_size(source.width, source.height);
+        let gop = (options.keyframe_interval * f64::from(frame_rate)).round().max(1.0) as u32;
+
+        // x264 wants planar 4:2:0 for the main profile, so convert anything else while resizing
+        let scaler = if source.format != format::Pixel::YUV420P
+            || source.width != width
+            || source.height != height
+        {
+            Some(scaling::Context::get(
+                source.format,
+                source.width,
+                source.height,
+                format::Pixel::YUV420P,
+                width,
+                height,
+                scaling::Flags::BILINEAR,
+            )?)
+        } else {
+            None
+        };
+
         encoder.set_width(width);
-        encoder.set_aspect_ratio(decoder.aspect_ratio());
-        encoder.set_format(decoder.format());
-        encoder.set_frame_rate(fps);
-        encoder.set_time_base(fps.unwrap().invert());
+        encoder.set_height(height);
+        encoder.set_aspect_ratio(source.aspect_ratio);
+        encoder.set_format(format::Pixel::YUV420P);
+        encoder.set_frame_rate(Some(frame_rate));
+        encoder.set_time_base(frame_rate.invert());
+        encoder.set_gop(gop);
+        encoder.set_bit_rate(options.video_bitrate);
+        encoder.set_max_bit_rate(options.max_bitrate());
         if global_header {
             encoder.set_flags(codec::Flags::GLOBAL_HEADER);
         }
 
-        encoder
-            .open_with(x264_opts)
-            .expect("error opening libx264 encoder with supplied settings");
-        encoder = codec::context::Context::from_parameters(ost.parameters())?
-            .encoder()
-            .video()?;
-        ost.set_parameters(&encoder);
+        let opened_encoder = encoder.open_with(x264_options(options, gop)?)?;
+        ost.set_parameters(&opened_encoder);
         Ok(Self {
             ost_index,
-            decoder,
-            encoder: codec::context::Context::from_parameters(ost.parameters())?
-                .encoder()
-                .video()?,
-            logging_enabled: enable_logging,
+            decoder: None,
+            input_time_base: source.frame_rate.invert(),
+            encoder: opened_encoder,
+            frame_rate,
+            scaler,
+            timestamp_offset: 0.0,
+            last_pts: None,
+            encoded_frames: 0,
+            encoded_bytes: 0,
+            dropped_frames: 0,
+            logging_enabled: options.log_progress,
             frame_count: 0,
             last_log_frame_count: 0,
             starting_time: Instant::now(),
@@ -75,56 +151,166 @@ impl Transcoder {
         })
     }
 
-    fn send_packet_to_decoder(&mut self, packet: &Packet) {
-        self.decoder.send_packet(packet).unwrap();
+    pub(crate) fn send_packet_to_decoder(&mut self, packet: &Packet) -> Result<(), ffmpeg_next::Error> {
+        match self.decoder.as_mut() {
+            Some(decoder) => decoder.send_packet(packet),
+            None => Ok(()),
+        }
     }
 
-    fn send_eof_to_decoder(&mut self) {
-        self.decoder.send_eof().unwrap();
+    pub(crate) fn send_eof_to_decoder(&mut self) -> Result<(), ffmpeg_next::Error> {
+        match self.decoder.as_mut() {
+            Some(decoder) => decoder.send_eof(),
+            None => Ok(()),
+        }
     }
 
-    fn receive_and_process_decoded_frames(
+    pub(crate) fn receive_and_process_decoded_frames(
         &mut self,
         octx: &mut format::context::Output,
         ost_time_base: Rational,
-    ) {
+    ) -> Result<(), ffmpeg_next::Error> {
         let mut frame = frame::Video::empty();
-        while self.decoder.receive_frame(&mut frame).is_ok() {
-            self.frame_count += 1;
-            let timestamp = frame.timestamp();
-            self.log_progress(f64::from(
-                Rational(timestamp.unwrap_or(0) as i32, 1) * self.decoder.time_base(),
-            ));
-            frame.set_pts(timestamp);
-            frame.set_kind(picture::Type::None);
-            self.send_frame_to_encoder(&frame);
-            self.receive_and_process_encoded_packets(octx, ost_time_base);
+        while self
+            .decoder
+            .as_mut()
+            .map_or(false, |decoder| decoder.receive_frame(&mut frame).is_ok())
+        {
+            let timestamp = frame.timestamp().unwrap_or(0) as f64 * f64::from(self.input_time_base)
+                + self.timestamp_offset;
+            self.encode_frame(&mut frame, timestamp, octx, ost_time_base)?;
         }
+        Ok(())
+    }
+
+    /// Switch to decoding another stream, keeping the encoder and its timeline
+    ///
+    /// # Parameters
+    /// * `ist` - The new video stream
+    /// * `timestamp_offset` - Seconds added to the stream's timestamps
+    pub(crate) fn attach(
+        &mut self,
+        ist: &format::stream::Stream,
+        timestamp_offset: f64,
+    ) -> Result<(), ffmpeg_next::Error> {
+        let decoder = ffmpeg_next::codec::context::Context::from_parameters(ist.parameters())?
+            .decoder()
+            .video()?;
+        let (width, height) = (self.encoder.width(), self.encoder.height());
+        self.scaler = if decoder.format() != format::Pixel::YUV420P
+            || decoder.width() != width
+            || decoder.height() != height
+        {
+            Some(scaling::Context::get(
+                decoder.format(),
+                decoder.width(),
+                decoder.height(),
+                format::Pixel::YUV420P,
+                width,
+                height,
+                scaling::Flags::BILINEAR,
+            )?)
+        } else {
+            None
+        };
+        self.decoder = Some(decoder);
+        self.input_time_base = ist.time_base();
+        self.timestamp_offset = timestamp_offset;
+        Ok(())
+    }
+
+    pub(crate) fn detach(&mut self) {
+        self.decoder = None;
     }
 
-    fn send_frame_to_encoder(&mut self, frame: &frame::Video) {
-        self.encoder.send_frame(frame).unwrap();
+    /// Where the next frame would start, in seconds
+    pub(crate) fn end_time(&self) -> f64 {
+        self.last_pts.map_or(0.0, |pts| (pts + 1) as f64 / f64::from(self.frame_rate))
     }
 
-    fn send_eof_to_encoder(&mut self) {
-        self.encoder.send_eof().unwrap();
+    /// Encode a black frame shown at `timestamp` seconds
+    pub(crate) fn encode_black(
+        &mut self,
+        timestamp: f64,
+        octx: &mut format::context::Output,
+        ost_time_base: Rational,
+    ) -> Result<(), ffmpeg_next::Error> {
+        let mut frame = frame::Video::new(
+            format::Pixel::YUV420P,
+            self.encoder.width(),
+            self.encoder.height(),
+        );
+        frame.data_mut(0).fill(16);
+        frame.data_mut(1).fill(128);
+        frame.data_mut(2).fill(128);
+        // The frame is already at the output size and format
+        let scaler = self.scaler.take();
+        let result = self.encode_frame(&mut frame, timestamp, octx, ost_time_base);
+        self.scaler = scaler;
+        result
     }
 
-    fn receive_and_process_encoded_packets(
+    /// Encode a frame shown at `timestamp` seconds
+    fn encode_frame(
         &mut self,
+        frame: &mut frame::Video,
+        timestamp: f64,
         octx: &mut format::context::Output,
         ost_time_base: Rational,
-    ) {
+    ) -> Result<(), ffmpeg_next::Error> {
+        self.frame_count += 1;
+        self.log_progress(timestamp);
+
+        // Retime to the output frame rate, dropping frames that land on an already sent slot
+        let pts = (timestamp * f64::from(self.frame_rate)).round() as i64;
+        if self.last_pts.map_or(false, |last| pts <= last) {
+            self.dropped_frames += 1;
+            return Ok(());
+        }
+        self.last_pts = Some(pts);
+
+        match self.scaler.as_mut() {
+            Some(scaler) => {
+                let mut scaled = frame::Video::empty();
+                scaler.run(frame, &mut scaled)?;
+                scaled.set_pts(Some(pts));
+                scaled.set_kind(picture::Type::None);
+                self.send_frame_to_encoder(&scaled)?;
+            }
+            None => {
+                frame.set_pts(Some(pts));
+                frame.set_kind(picture::Type::None);
+                self.send_frame_to_encoder(frame)?;
+            }
+        }
+        self.receive_and_process_encoded_packets(octx, ost_time_base)
+    }
+
+    fn send_frame_to_encoder(&mut self, frame: &frame::Video) -> Result<(), ffmpeg_next::Error> {
+        self.encoder.send_frame(frame)
+    }
+
+    pub(crate) fn send_eof_to_encoder(&mut self) -> Result<(), ffmpeg_next::Error> {
+        self.encoder.send_eof()
+    }
+
+    pub(crate) fn receive_and_process_encoded_packets(
+        &mut self,
+        octx: &mut format::context::Output,
+        ost_time_base: Rational,
+    ) -> Result<(), ffmpeg_next::Error> {
         let mut encoded = Packet::empty();
         while self.encoder.receive_packet(&mut encoded).is_ok() {
             encoded.set_stream(self.ost_index);
-            encoded.rescale_ts(self.decoder.time_base(), ost_time_base);
-            encoded.write_interleaved(octx).unwrap();
+            encoded.rescale_ts(self.frame_rate.invert(), ost_time_base);
+            self.encoded_frames += 1;
+            self.encoded_bytes += encoded.size() as u64;
+            encoded.write_interleaved(octx)?;
         }
+        Ok(())
     }
 
     fn log_progress(&mut self, timestamp: f64) {
-        println!("logging progress");
         if !self.logging_enabled
             || (self.frame_count - self.last_log_frame_count < 100
                 && self.last_log_time.elapsed().as_secs_f64() < 1.0)
@@ -132,7 +318,7 @@ impl Transcoder {
             return;
         }
         eprintln!(
-            "time elpased: \t{:8.2}\tframe count: {:8}\ttimestamp: {:8.2}",
+            "time elapsed: \t{:8.2}\tframe count: {:8}\ttimestamp: {:8.2}",
             self.starting_time.elapsed().as_secs_f64(),
             self.frame_count,
             timestamp
@@ -142,194 +328,1058 @@ impl Transcoder {
     }
 }
 
+/// Re-encodes an audio stream to AAC, resampling and remixing to what Livepeer ingest takes
+pub(crate) struct AudioTranscoder {
+    ost_index: usize,
+    decoder: Option<decoder::Audio>,
+    input_time_base: Rational,
+    filter: Option<filter::Graph>,
+    encoder: encoder::Audio,
+    sample_rate: i32,
+    pub(crate) next_pts: Option<i64>,
+    pub(crate) encoded_bytes: u64,
+}
+
+impl AudioTranscoder {
+    fn new(
+        ist: &format::stream::Stream,
+        octx: &mut format::context::Output,
+        ost_index: usize,
+        options: &PushOptions,
+    ) -> Result<Self, errors::Error> {
+        let decoder = ffmpeg_next::codec::context::Context::from_parameters(ist.parameters())?
+            .decoder()
+            .audio()?;
+
+        // FLV only signals 44.1kHz for AAC but players follow the AudioSpecificConfig, so 48kHz
+        // works too. Anything else is resampled to 48kHz and surround is downmixed to stereo.
+        let sample_rate = match decoder.rate() {
+            44100 | 48000 => decoder.rate() as i32,
+            _ => 48000,
+        };
+        let channel_layout = match decoder.channels() {
+            1 => ChannelLayout::MONO,
+            _ => ChannelLayout::STEREO,
+        };
+        let mut transcoder =
+            Self::from_frames(octx, ost_index, sample_rate, channel_layout, options)?;
+        transcoder.filter = Some(audio_filter(&decoder, ist.time_base(), &transcoder.encoder)?);
+        transcoder.decoder = Some(decoder);
+        transcoder.input_time_base = ist.time_base();
+        Ok(transcoder)
+    }
+
+    /// A transcoder fed with raw frames through `encode_frame` rather than packets
+    ///
+    /// Frames must match the encoder's sample format, layout and frame size.
+    pub(crate) fn from_frames(
+        octx: &mut format::context::Output,
+        ost_index: usize,
+        sample_rate: i32,
+        channel_layout: ChannelLayout,
+        options: &PushOptions,
+    ) -> Result<Self, ffmpeg_next::Error> {
+        let global_header = octx.format().flags().contains(format::Flags::GLOBAL_HEADER);
+        let codec = encoder::find(codec::Id::AAC)
+            .ok_or(ffmpeg_next::Error::EncoderNotFound)?
+            .audio()?;
+        let mut ost = octx.add_stream(codec)?;
+        let mut encoder = codec::context::Context::from_parameters(ost.parameters())?
+            .encoder()
+            .audio()?;
+
+        encoder.set_rate(sample_rate);
+        encoder.set_channel_layout(channel_layout);
+        encoder.set_channels(channel_layout.channels());
+        encoder.set_format(
+            codec
+                .formats()
+                .and_then(|mut formats| formats.next())
+                .unwrap_or(format::Sample::F32(format::sample::Type::Planar)),
+        );
+        encoder.set_bit_rate(options.audio_bitrate);
+        encoder.set_time_base(Rational(1, sample_rate));
+        ost.set_time_base(Rational(1, sample_rate));
+        if global_header {
+            encoder.set_flags(codec::Flags::GLOBAL_HEADER);
+        }
+
+        let opened_encoder = encoder.open_as(codec)?;
+        ost.set_parameters(&opened_encoder);
+        Ok(Self {
+            ost_index,
+            decoder: None,
+            input_time_base: Rational(1, sample_rate),
+            filter: None,
+            encoder: opened_encoder,
+            sample_rate,
+            next_pts: None,
+            encoded_bytes: 0,
+        })
+    }
+
+    pub(crate) fn send_packet_to_decoder(&mut self, packet: &Packet) -> Result<(), ffmpeg_next::Error> {
+        match self.decoder.as_mut() {
+            Some(decoder) => decoder.send_packet(packet),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn send_eof_to_decoder(&mut self) -> Result<(), ffmpeg_next::Error> {
+        match self.decoder.as_mut() {
+            Some(decoder) => decoder.send_eof(),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn receive_and_process_decoded_frames(
+        &mut self,
+        octx: &mut format::context::Output,
+        ost_time_base: Rational,
+    ) -> Result<(), errors::Error> {
+        let mut frame = frame::Audio::empty();
+        while self
+            .decoder
+            .as_mut()
+            .map_or(false, |decoder| decoder.receive_frame(&mut frame).is_ok())
+        {
+            if self.next_pts.is_none() {
+                let timestamp =
+                    frame.timestamp().unwrap_or(0) as f64 * f64::from(self.input_time_base);
+                self.next_pts = Some((timestamp * self.sample_rate as f64).round() as i64);
+            }
+            let timestamp = frame.timestamp();
+            frame.set_pts(timestamp);
+            if let Some(filter) = self.filter.as_mut() {
+                filter_context(filter, "in")?.source().add(&frame)?;
+            }
+            self.receive_and_process_filtered_frames(octx, ost_time_base)?;
+        }
+        Ok(())
+    }
+
+    fn flush_filter(&mut self) -> Result<(), errors::Error> {
+        match self.filter.as_mut() {
+            Some(filter) => Ok(filter_context(filter, "in")?.source().flush()?),
+            None => Ok(()),
+        }
+    }
+
+    fn receive_and_process_filtered_frames(
+        &mut self,
+        octx: &mut format::context::Output,
+        ost_time_base: Rational,
+    ) -> Result<(), errors::Error> {
+        let mut filtered = frame::Audio::empty();
+        while let Some(filter) = self.filter.as_mut() {
+            if filter_context(filter, "out")?.sink().frame(&mut filtered).is_err() {
+                break;
+            }
+            self.encode_frame(&mut filtered, octx, ost_time_base)?;
+        }
+        Ok(())
+    }
+
+    /// Encode a frame following on from the previous one
+    fn encode_frame(
+        &mut self,
+        frame: &mut frame::Audio,
+        octx: &mut format::context::Output,
+        ost_time_base: Rational,
+    ) -> Result<(), ffmpeg_next::Error> {
+        // Count samples rather than trust the source timestamps, so the AAC track has no gaps
+        let pts = self.next_pts.unwrap_or(0);
+        frame.set_pts(Some(pts));
+        self.next_pts = Some(pts + frame.samples() as i64);
+        self.encoder.send_frame(frame)?;
+        self.receive_and_process_encoded_packets(octx, ost_time_base)
+    }
+
+    /// Switch to decoding another stream, carrying on from the samples already sent
+    ///
+    /// Samples still buffered for the previous stream, less than one frame, are dropped.
+    pub(crate) fn attach(&mut self, ist: &format::stream::Stream) -> Result<(), errors::Error> {
+        let decoder = ffmpeg_next::codec::context::Context::from_parameters(ist.parameters())?
+            .decoder()
+            .audio()?;
+        self.filter = Some(audio_filter(&decoder, ist.time_base(), &self.encoder)?);
+        self.decoder = Some(decoder);
+        self.input_time_base = ist.time_base();
+        self.next_pts.get_or_insert(0);
+        Ok(())
+    }
+
+    pub(crate) fn detach(&mut self) {
+        self.decoder = None;
+        self.filter = None;
+    }
+
+    /// Where the next frame would start, in seconds
+    pub(crate) fn end_time(&self) -> f64 {
+        self.next_pts.unwrap_or(0) as f64 / self.sample_rate as f64
+    }
+
+    /// Encode silence until `until` seconds
+    pub(crate) fn fill_silence(
+        &mut self,
+        until: f64,
+        octx: &mut format::context::Output,
+        ost_time_base: Rational,
+    ) -> Result<(), ffmpeg_next::Error> {
+        let frame_size = (self.encoder.frame_size() as usize).max(1);
+        while self.end_time() < until {
+            let mut frame =
+                frame::Audio::new(self.encoder.format(), frame_size, self.encoder.channel_layout());
+            frame.set_rate(self.sample_rate as u32);
+            // Zero is silence for every sample format the AAC encoder takes
+            for plane in 0..frame.planes() {
+                frame.data_mut(plane).fill(0);
+            }
+            self.encode_frame(&mut frame, octx, ost_time_base)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn send_eof_to_encoder(&mut self) -> Result<(), ffmpeg_next::Error> {
+        self.encoder.send_eof()
+    }
+
+    pub(crate) fn receive_and_process_encoded_packets(
+        &mut self,
+        octx: &mut format::context::Output,
+        ost_time_base: Rational,
+    ) -> Result<(), ffmpeg_next::Error> {
+        let mut encoded = Packet::empty();
+        while self.encoder.receive_packet(&mut encoded).is_ok() {
+            encoded.set_stream(self.ost_index);
+            encoded.rescale_ts(Rational(1, self.sample_rate), ost_time_base);
+            self.encoded_bytes += encoded.size() as u64;
+            encoded.write_interleaved(octx)?;
+        }
+        Ok(())
+    }
+}
+
+/// A filter graph converting decoded audio to the encoder's format, rate, layout and frame size
+fn audio_filter(
+    decoder: &decoder::Audio,
+    time_base: Rational,
+    encoder: &encoder::Audio,
+) -> Result<filter::Graph, errors::Error> {
+    let mut graph = filter::Graph::new();
+    let channel_layout = if decoder.channel_layout().is_empty() {
+        ChannelLayout::default(decoder.channels() as i32)
+    } else {
+        decoder.channel_layout()
+    };
+    let args = format!(
+        "time_base={}:sample_rate={}:sample_fmt={}:channel_layout=0x{:x}",
+        time_base,
+        decoder.rate(),
+        decoder.format().name(),
+        channel_layout.bits()
+    );
+    let filters = [("in", "abuffer", args.as_str()), ("out", "abuffersink", "")];
+    for (name, filter_name, args) in filters {
+        let filter = filter::find(filter_name)
+            .ok_or_else(|| errors::Error::FFMPEG(format!("no {} filter", filter_name)))?;
+        graph.add(&filter, name, args)?;
+    }
+    {
+        let mut out = filter_context(&mut graph, "out")?;
+        out.set_sample_format(encoder.format());
+        out.set_channel_layout(encoder.channel_layout());
+        out.set_sample_rate(encoder.rate());
+    }
+    graph.output("in", 0)?.input("out", 0)?.parse("anull")?;
+    graph.validate()?;
+    // The AAC encoder only takes whole frames of `frame_size` samples
+    filter_context(&mut graph, "out")?
+        .sink()
+        .set_frame_size(encoder.frame_size());
+    Ok(graph)
+}
+
+/// A filter of an audio graph by name, `in` or `out`
+fn filter_context<'a>(
+    graph: &'a mut filter::Graph,
+    name: &str,
+) -> Result<filter::Context<'a>, errors::Error> {
+    graph
+        .get(name)
+        .ok_or_else(|| errors::Error::FFMPEG(format!("audio filter graph has no {} filter", name)))
+}
+
 fn parse_opts<'a>(s: String) -> Option<Dictionary<'a>> {
     let mut dict = Dictionary::new();
+    // Split on the first `=` only, `x264-params` values are themselves `key=value` lists
     for keyval in s.split_terminator(',') {
-        let tokens: Vec<&str> = keyval.split('=').collect();
-        match tokens[..] {
-            [key, val] => dict.set(key, val),
-            _ => return None,
+        match keyval.split_once('=') {
+            Some((key, val)) => dict.set(key, val),
+            None => return None,
         }
     }
-    println!("DICT {:?}", dict);
     Some(dict)
 }
 
+/// Encoder options for the x264 settings, with keyframes pinned to the GOP size
+///
+/// `x264-params` given in `x264_opts` are kept ahead of the keyframe settings, except the ones
+/// `PushOptions` sets, which `validate` rejects.
+fn x264_options<'a>(options: &PushOptions, gop: u32) -> Result<Dictionary<'a>, ffmpeg_next::Error> {
+    let mut dict = match &options.x264_opts {
+        Some(opts) => parse_opts(opts.to_string()).ok_or(ffmpeg_next::Error::OptionNotFound)?,
+        None => Dictionary::new(),
+    };
+    let extra_params: String = dict
+        .get("x264-params")
+        .map(|params| {
+            params
+                .split(':')
+                .filter(|param| !param.is_empty())
+                .zip(x264_params(params))
+                .filter(|(_, (key, _))| !X264_RESERVED_PARAMS.contains(&key.as_str()))
+                .map(|(param, _)| format!("{}:", param))
+                .collect()
+        })
+        .unwrap_or_default();
+    dict.set("preset", &options.preset);
+    if let Some(tune) = &options.tune {
+        dict.set("tune", tune);
+    }
+    if let Some(profile) = &options.profile {
+        dict.set("profile", profile);
+    }
+    dict.set("bufsize", &options.buffer_size().to_string());
+    // Scene cuts would add keyframes off the interval, which breaks Livepeer's segmenting
+    dict.set(
+        "x264-params",
+        &format!(
+            "{}keyint={}:min-keyint={}:scenecut=0",
+            extra_params,
+            gop,
+            gop
+        ),
+    );
+    Ok(dict)
+}
+
+/// The average frame rate of a stream, falling back to its base rate and then to 30fps
+pub(crate) fn source_frame_rate(stream: &format::stream::Stream) -> Rational {
+    [stream.avg_frame_rate(), stream.rate()]
+        .into_iter()
+        .find(|rate| rate.numerator() > 0 && rate.denominator() > 0)
+        .unwrap_or(Rational(FALLBACK_FPS, 1))
+}
+
+/// Sleeps so packets go out no faster than their timestamps, like ffmpeg's `-re`
+pub(crate) struct Pacer {
+    started: Instant,
+    first_timestamp: Option<f64>,
+}
+
+impl Pacer {
+    pub(crate) fn new() -> Self {
+        Pacer {
+            started: Instant::now(),
+            first_timestamp: None,
+        }
+    }
+
+    /// Wait until the wall clock catches up with a timestamp, in seconds
+    ///
+    /// Returns the media time since the first timestamp.
+    pub(crate) fn wait(&mut self, timestamp: f64) -> f64 {
+        let first = *self.first_timestamp.get_or_insert(timestamp);
+        let offset = (timestamp - first).max(0.0);
+        let target = Duration::from_secs_f64(offset);
+        let elapsed = self.started.elapsed();
+        if target > elapsed {
+            std::thread::sleep(target - elapsed);
+        }
+        offset
+    }
+}
+
 /// Live RTMP Methods
 ///
 impl Rtmp {
-    pub fn push(self: Self, stream_key: &String, file_path: &String) {
-        let output_file = format!("{}/{}", self.client.config.rtmp_endpoint, stream_key);
-        ffmpeg_next::init().unwrap();
-        let mut ictx = format::input(&file_path).unwrap();
-        let mut octx = format::output(&"output.mp4").unwrap();
-
-        let best_video_stream_index = ictx
-            .streams()
-            .best(media::Type::Video)
-            .map(|stream| stream.index());
-        let mut stream_mapping: Vec<isize> = vec![0; ictx.nb_streams() as _];
-        let mut ist_time_bases = vec![Rational(0, 0); ictx.nb_streams() as _];
-        let mut ost_time_bases = vec![Rational(0, 0); ictx.nb_streams() as _];
-        let mut transcoders = HashMap::new();
-        let mut ost_index = 0;
-        for (ist_index, ist) in ictx.streams().enumerate() {
-            let ist_medium = ist.parameters().medium();
-            if ist_medium != media::Type::Audio
-                && ist_medium != media::Type::Video
-                && ist_medium != media::Type::Subtitle
-            {
-                stream_mapping[ist_index] = -1;
-                continue;
-            }
-            stream_mapping[ist_index] = ost_index;
-            ist_time_bases[ist_index] = ist.time_base();
-            if ist_medium == media::Type::Video {
-                // Initialize transcoder for video stream.
-                transcoders.insert(
-                    ist_index,
-                    Transcoder::new(
-                        &ist,
-                        &mut octx,
-                        ost_index as _,
-                        parse_opts(DEFAULT_X264_OPTS.to_string())
-                            .to_owned()
-                            .unwrap(),
-                        true,
-                    )
-                    .unwrap(),
-                );
-            } else {
-                // Set up for stream copy for non-video stream.
-                let mut ost = octx.add_stream(encoder::find(codec::Id::None)).unwrap();
-                ost.set_parameters(ist.parameters());
-                // We need to set codec_tag to 0 lest we run into incompatible codec tag
-                // issues when muxing into a different container format. Unfortunately
-                // there's no high level API to do this (yet).
-                unsafe {
-                    (*ost.parameters().as_mut_ptr()).codec_tag = 0;
-                }
-            }
-            ost_index += 1;
+    /// Push a file to the RTMP ingest with the default `PushOptions`
+    ///
+    /// Video is transcoded to H.264, audio is transcoded to AAC unless it already is, and the
+    /// result is muxed as FLV to `<rtmp_endpoint>/<stream_key>`. Subtitle, data and extra
+    /// audio or video tracks are dropped. Packets are sent in realtime, so this blocks for the
+    /// duration of the file.
+    ///
+    /// # Parameters
+    /// * `stream_key` - The stream key
+    /// * `file_path` - The file to push
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result once the whole file was sent, or an error
+    pub fn push(self: Self, stream_key: &String, file_path: &String) -> Result<(), errors::Error> {
+        self.push_with_options(stream_key, file_path, &PushOptions::default())
+    }
+
+    /// Push a file to the RTMP ingest with custom encoder settings
+    ///
+    /// # Parameters
+    /// * `stream_key` - The stream key
+    /// * `file_path` - The file to push
+    /// * `options` - Encoder settings, validated before anything is sent
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result once the whole file was sent, or an error
+    pub fn push_with_options(
+        self: Self,
+        stream_key: &String,
+        file_path: &String,
+        options: &PushOptions,
+    ) -> Result<(), errors::Error> {
+        options.validate()?;
+        let output_url = format!("{}/{}", self.client.config.rtmp_endpoint, stream_key);
+        push_file(file_path, &output_url, "flv", options, &PushControl::detached())
+    }
+
+    /// Push a file to the RTMP ingest in the background
+    ///
+    /// # Parameters
+    /// * `stream_key` - The stream key
+    /// * `file_path` - The file to push
+    /// * `options` - Encoder settings, validated before the push starts
+    ///
+    /// # Returns
+    /// * `Result<PushHandle, errors::Error>` - A handle to stop, wait on or follow the push, or a `PUSHOPTIONS` error
+    pub fn spawn(
+        self: &Self,
+        stream_key: &String,
+        file_path: &String,
+        options: &PushOptions,
+    ) -> Result<PushHandle, errors::Error> {
+        options.validate()?;
+        let output_url = format!("{}/{}", self.client.config.rtmp_endpoint, stream_key);
+        let file_path = file_path.clone();
+        let options = options.clone();
+        Ok(PushHandle::spawn_thread(stream_key, move |control| {
+            push_file(&file_path, &output_url, "flv", &options, &control)
+        }))
+    }
+
+    /// Push a muxed stream read from any source to the RTMP ingest
+    ///
+    /// The source is read front to back, so it must be a streamable container such as MPEG-TS
+    /// or FLV. Packets are still paced by their timestamps, so in-memory buffers go out in
+    /// realtime too.
+    ///
+    /// # Parameters
+    /// * `stream_key` - The stream key
+    /// * `reader` - The source, e.g. `std::io::stdin()`, a pipe or a `Cursor`
+    /// * `format_name` - The container, e.g. `mpegts` or `flv`, probed from the data when `None`
+    /// * `options` - Encoder settings, validated before anything is read
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result once the source ended, or an error
+    pub fn push_reader<R: Read + Send + 'static>(
+        self: &Self,
+        stream_key: &String,
+        reader: R,
+        format_name: Option<&str>,
+        options: &PushOptions,
+    ) -> Result<(), errors::Error> {
+        options.validate()?;
+        let output_url = format!("{}/{}", self.client.config.rtmp_endpoint, stream_key);
+        let mut input = ReaderInput::open(reader, format_name)?;
+        push_input(&mut input, &output_url, "flv", options, &PushControl::detached())
+    }
+
+    /// Push a muxed stream read from any source to the RTMP ingest, in the background
+    ///
+    /// # Parameters
+    /// * `stream_key` - The stream key
+    /// * `reader` - The source, e.g. `std::io::stdin()`, a pipe or a `Cursor`
+    /// * `format_name` - The container, e.g. `mpegts` or `flv`, probed from the data when `None`
+    /// * `options` - Encoder settings, validated before the push starts
+    ///
+    /// # Returns
+    /// * `Result<PushHandle, errors::Error>` - A handle to the push, or a `PUSHOPTIONS` error
+    pub fn spawn_reader<R: Read + Send + 'static>(
+        self: &Self,
+        stream_key: &String,
+        reader: R,
+        format_name: Option<&str>,
+        options: &PushOptions,
+    ) -> Result<PushHandle, errors::Error> {
+        options.validate()?;
+        let output_url = format!("{}/{}", self.client.config.rtmp_endpoint, stream_key);
+        let format_name = format_name.map(|name| name.to_string());
+        let options = options.clone();
+        Ok(PushHandle::spawn_thread(stream_key, move |control| {
+            // Probing blocks on the source, so it happens on the push thread
+            let mut input = ReaderInput::open(reader, format_name.as_deref())?;
+            push_input(&mut input, &output_url, "flv", &options, &control)
+        }))
+    }
+
+    /// Push a generated test pattern and tone to the RTMP ingest, for load testing without media files
+    ///
+    /// # Parameters
+    /// * `stream_key` - The stream key
+    /// * `pattern` - The pattern to generate
+    /// * `options` - Encoder settings, validated before anything is sent
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result once the pattern's duration was sent, or an error
+    pub fn push_test_pattern(
+        self: &Self,
+        stream_key: &String,
+        pattern: &TestPattern,
+        options: &PushOptions,
+    ) -> Result<(), errors::Error> {
+        options.validate()?;
+        pattern.validate()?;
+        let output_url = format!("{}/{}", self.client.config.rtmp_endpoint, stream_key);
+        push_pattern(pattern, &output_url, "flv", options, &PushControl::detached())
+    }
+
+    /// Push a generated test pattern and tone to the RTMP ingest, in the background
+    ///
+    /// # Parameters
+    /// * `stream_key` - The stream key
+    /// * `pattern` - The pattern to generate, with no duration it runs until stopped
+    /// * `options` - Encoder settings, validated before the push starts
+    ///
+    /// # Returns
+    /// * `Result<PushHandle, errors::Error>` - A handle to the push, or a `PUSHOPTIONS` error
+    pub fn spawn_test_pattern(
+        self: &Self,
+        stream_key: &String,
+        pattern: &TestPattern,
+        options: &PushOptions,
+    ) -> Result<PushHandle, errors::Error> {
+        options.validate()?;
+        pattern.validate()?;
+        let output_url = format!("{}/{}", self.client.config.rtmp_endpoint, stream_key);
+        let pattern = pattern.clone();
+        let options = options.clone();
+        Ok(PushHandle::spawn_thread(stream_key, move |control| {
+            push_pattern(&pattern, &output_url, "flv", &options, &control)
+        }))
+    }
+
+    /// Push a playlist of files to the RTMP ingest over one connection, for an always-on channel
+    ///
+    /// Every item is re-encoded to the same size and frame rate with continuous timestamps, so
+    /// the stream doesn't drop between items. Output size and frame rate default to those of the
+    /// first item. A looped playlist runs until an error.
+    ///
+    /// # Parameters
+    /// * `stream_key` - The stream key
+    /// * `playlist` - The files, validated before anything is sent
+    /// * `options` - Encoder settings, validated before anything is sent
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result once the playlist was sent, or an error
+    pub fn push_playlist(
+        self: &Self,
+        stream_key: &String,
+        playlist: &Playlist,
+        options: &PushOptions,
+    ) -> Result<(), errors::Error> {
+        options.validate()?;
+        playlist.validate()?;
+        let output_url = format!("{}/{}", self.client.config.rtmp_endpoint, stream_key);
+        push_playlist(playlist, &output_url, "flv", options, &PushControl::detached())
+    }
+
+    /// Push a playlist of files to the RTMP ingest in the background
+    ///
+    /// The handle reports `PushEvent::ItemStarted` as each item goes out.
+    ///
+    /// # Parameters
+    /// * `stream_key` - The stream key
+    /// * `playlist` - The files, a looped playlist runs until stopped
+    /// * `options` - Encoder settings, validated before the push starts
+    ///
+    /// # Returns
+    /// * `Result<PushHandle, errors::Error>` - A handle to the push, or a `PUSHOPTIONS` error
+    pub fn spawn_playlist(
+        self: &Self,
+        stream_key: &String,
+        playlist: &Playlist,
+        options: &PushOptions,
+    ) -> Result<PushHandle, errors::Error> {
+        options.validate()?;
+        playlist.validate()?;
+        let output_url = format!("{}/{}", self.client.config.rtmp_endpoint, stream_key);
+        let playlist = playlist.clone();
+        let options = options.clone();
+        Ok(PushHandle::spawn_thread(stream_key, move |control| {
+            push_playlist(&playlist, &output_url, "flv", &options, &control)
+        }))
+    }
+
+    /// Push a file, reconnecting when the connection drops and failing over between ingests
+    ///
+    /// After a dropped connection the push resumes from where it was, on the same endpoint first.
+    /// Refused connections back off, and move on to the next endpoint of the policy after
+    /// `attempts_per_endpoint` failures. The stream key is the same on every endpoint.
+    ///
+    /// # Parameters
+    /// * `stream_key` - The stream key
+    /// * `file_path` - The file to push
+    /// * `options` - Encoder settings, validated before the push starts
+    /// * `policy` - Endpoints and retry settings, e.g. built with `region_endpoints`
+    ///
+    /// # Returns
+    /// * `Result<PushHandle, errors::Error>` - A handle to the push, reporting `PushEvent::Reconnecting` before each retry, or a `PUSHOPTIONS` error
+    pub fn spawn_with_failover(
+        self: &Self,
+        stream_key: &String,
+        file_path: &String,
+        options: &PushOptions,
+        policy: &FailoverPolicy,
+    ) -> Result<PushHandle, errors::Error> {
+        options.validate()?;
+        policy.validate()?;
+        let key = stream_key.clone();
+        let file_path = file_path.clone();
+        let options = options.clone();
+        let policy = policy.clone();
+        Ok(PushHandle::spawn_thread(stream_key, move |control| {
+            supervise(&key, &policy, &control, |output_url, start, control| {
+                push_file_from(&file_path, start, output_url, "flv", &options, control)
+            })
+        }))
+    }
+
+    /// The RTMP ingests of a list of regions, in the same order, for a `FailoverPolicy`
+    ///
+    /// # Parameters
+    /// * `regions` - Region codes such as `lax` or `fra`, or `none` for the global endpoint
+    ///
+    /// # Returns
+    /// * `Vec<String>` - The ingest endpoints, without stream key
+    pub fn region_endpoints(self: &Self, regions: &[&str]) -> Vec<String> {
+        regions
+            .iter()
+            .map(|region| self.region_endpoint(region))
+            .collect()
+    }
+
+    /// Push a file to a regional ingest with an ffmpeg process, blocking until it exits
+    ///
+    /// # Parameters
+    /// * `stream_key` - The stream key
+    /// * `file_path` - The file to push
+    /// * `region` - The ingest region, or `none` for the global endpoint
+    /// * `ffmpeg_path` - The ffmpeg binary
+    /// * `proc_id` - An ID for the push, set to a random one when `None`
+    ///
+    /// # Returns
+    /// * `Result<String, String>` - The ffmpeg exit status, as an error if it failed
+    pub fn push_to_region(
+        self: &Self,
+        stream_key: &String,
+        file_path: &String,
+        region: &String,
+        ffmpeg_path: &String,
+        proc_id: &mut Option<String>,
+    ) -> Result<String, String> {
+        let mut handle = self
+            .spawn_to_region(stream_key, file_path, region, ffmpeg_path, proc_id)
+            .map_err(|e| format!("{:?}", e))?;
+        let status = handle.wait().map_err(|e| format!("{:?}", e))?;
+        let exit = handle
+            .exit_status()
+            .map(|exit| exit.to_string())
+            .unwrap_or_default();
+        match status {
+            PushStatus::Completed => Ok(exit),
+            _ => Err(exit),
+        }
+    }
+
+    /// Push a file to a regional ingest with an ffmpeg process, in the background
+    ///
+    /// The ffmpeg progress output is parsed into `PushEvent`s on the handle.
+    ///
+    /// # Parameters
+    /// * `stream_key` - The stream key
+    /// * `file_path` - The file to push
+    /// * `region` - The ingest region, or `none` for the global endpoint
+    /// * `ffmpeg_path` - The ffmpeg binary
+    /// * `proc_id` - An ID for the push, also set as the stream title, set to a random one when `None`
+    ///
+    /// # Returns
+    /// * `Result<PushHandle, errors::Error>` - A handle to the running process, or an `IO` error if ffmpeg couldn't be started
+    pub fn spawn_to_region(
+        self: &Self,
+        stream_key: &String,
+        file_path: &String,
+        region: &String,
+        ffmpeg_path: &String,
+        proc_id: &mut Option<String>,
+    ) -> Result<PushHandle, errors::Error> {
+        let title = proc_id
+            .get_or_insert_with(|| format!("{:x}", rand::random::<u32>()))
+            .clone();
+
+        let mut cmd = std::process::Command::new(ffmpeg_path);
+        cmd.arg("-hide_banner")
+            .arg("-nostats")
+            .arg("-progress")
+            .arg("pipe:1")
+            .arg("-re")
+            .arg("-i")
+            .arg(file_path)
+            .arg("-metadata")
+            .arg(format!("title={}", title))
+            .arg("-c:v")
+            .arg("copy")
+            .arg("-c:a")
+            .arg("copy")
+            .arg("-f")
+            .arg("flv")
+            .arg(format!("{}/{}", self.region_endpoint(region), stream_key));
+        PushHandle::spawn_process(cmd, &title)
+    }
+
+    /// The RTMP ingest for a region, e.g. `rtmp://lax-rtmp.livepeer.com/live`
+    fn region_endpoint(self: &Self, region: &str) -> String {
+        if region != "none" {
+            self.client
+                .config
+                .rtmp_endpoint
+                .replace("rtmp://", &format!("rtmp://{}-", region))
+        } else {
+            self.client.config.rtmp_endpoint.to_string()
         }
+    }
+}
 
-        octx.set_metadata(ictx.metadata().to_owned());
-        format::context::output::dump(&octx, 0, Some(&output_file));
-        octx.write_header().unwrap();
+/// Push a file to an ingest URL, transcoding to H.264 and AAC and sending in realtime
+///
+/// Stops early, still closing the stream cleanly, once `control` is stopped.
+pub(crate) fn push_file(
+    file_path: &String,
+    output_url: &String,
+    output_format: &str,
+    options: &PushOptions,
+    control: &PushControl,
+) -> Result<(), errors::Error> {
+    push_file_from(file_path, 0.0, output_url, output_format, options, control)
+}
+
+/// Push a file from a point in it, like `push_file`
+///
+/// The push starts at the last keyframe before `start`, in seconds.
+pub(crate) fn push_file_from(
+    file_path: &String,
+    start: f64,
+    output_url: &String,
+    output_format: &str,
+    options: &PushOptions,
+    control: &PushControl,
+) -> Result<(), errors::Error> {
+    ffmpeg_next::init()?;
+    let mut ictx = format::input(&file_path)?;
+    if start > 0.0 {
+        let ts = (start * ffmpeg_next::ffi::AV_TIME_BASE as f64) as i64;
+        ictx.seek(ts, ..ts)?;
+    }
+    push_input(&mut ictx, output_url, output_format, options, control)
+}
 
-        for (ost_index, _) in octx.streams().enumerate() {
-            ost_time_bases[ost_index] = octx.stream(ost_index as _).unwrap().time_base();
+/// Push an opened input to an ingest URL, like `push_file`
+pub(crate) fn push_input(
+    ictx: &mut format::context::Input,
+    output_url: &String,
+    output_format: &str,
+    options: &PushOptions,
+    control: &PushControl,
+) -> Result<(), errors::Error> {
+    push_input_with(ictx, output_url, output_format, Dictionary::new(), options, control)
+}
+
+/// Push an opened input like `push_input`, passing options to the muxer
+pub(crate) fn push_input_with(
+    ictx: &mut format::context::Input,
+    output_url: &String,
+    output_format: &str,
+    muxer_options: Dictionary,
+    options: &PushOptions,
+    control: &PushControl,
+) -> Result<(), errors::Error> {
+    let mut octx = format::output_as(output_url, output_format)?;
+
+    let mut ist_time_bases = vec![Rational(0, 0); ictx.nb_streams() as _];
+    let mut ost_time_bases = vec![Rational(0, 0); ictx.nb_streams() as _];
+    let mut transcoders = HashMap::new();
+    let mut audio_transcoders = HashMap::new();
+    let best_video = ictx.streams().best(media::Type::Video).map(|s| s.index());
+    let best_audio = ictx.streams().best(media::Type::Audio).map(|s| s.index());
+    let stream_mapping = map_streams(ictx.nb_streams() as _, best_video, best_audio);
+    for (ist_index, ist) in ictx.streams().enumerate() {
+        let ost_index = stream_mapping[ist_index];
+        if ost_index < 0 {
+            continue;
+        }
+        ist_time_bases[ist_index] = ist.time_base();
+        if Some(ist_index) == best_video {
+            // Initialize transcoder for video stream.
+            transcoders.insert(
+                ist_index,
+                Transcoder::new(&ist, &mut octx, ost_index as _, options)?,
+            );
+        } else if options.audio_codec == AudioCodec::Aac
+            && ist.parameters().id() != codec::Id::AAC
+        {
+            audio_transcoders.insert(
+                ist_index,
+                AudioTranscoder::new(&ist, &mut octx, ost_index as _, options)?,
+            );
+        } else {
+            // Set up for stream copy for AAC audio, or any audio with `AudioCodec::Copy`.
+            let mut ost = octx.add_stream(encoder::find(codec::Id::None))?;
+            ost.set_parameters(ist.parameters());
+            // We need to set codec_tag to 0 lest we run into incompatible codec tag
+            // issues when muxing into a different container format. Unfortunately
+            // there's no high level API to do this (yet).
+            unsafe {
+                (*ost.parameters().as_mut_ptr()).codec_tag = 0;
+            }
         }
+    }
 
+    octx.set_metadata(ictx.metadata().to_owned());
+    octx.write_header_with(muxer_options)?;
+    control.connected();
+
+    for (ost_index, _) in octx.streams().enumerate() {
+        ost_time_bases[ost_index] = octx.stream(ost_index as _).unwrap().time_base();
+    }
+
+    let mut pacer = Pacer::new();
+    let mut meter = ProgressMeter::new();
+    let mut copied_bytes = 0;
+    let mut out_time = 0.0;
+    let mut send = || -> Result<(), errors::Error> {
         for (stream, mut packet) in ictx.packets() {
+            if control.is_stopped() {
+                break;
+            }
             let ist_index = stream.index();
             let ost_index = stream_mapping[ist_index];
             if ost_index < 0 {
                 continue;
             }
+            if let Some(ts) = packet.dts().or(packet.pts()) {
+                let ts = ts as f64 * f64::from(stream.time_base());
+                out_time = pacer.wait(ts);
+            }
+            if meter.is_due() {
+                let (frames, dropped, bytes) =
+                    push_totals(&transcoders, &audio_transcoders, copied_bytes);
+                control.report(meter.measure(frames, dropped, bytes, out_time));
+            }
             let ost_time_base = ost_time_bases[ost_index as usize];
-            match transcoders.get_mut(&ist_index) {
+            if let Some(transcoder) = transcoders.get_mut(&ist_index) {
+                transcoder.send_packet_to_decoder(&packet)?;
+                transcoder.receive_and_process_decoded_frames(&mut octx, ost_time_base)?;
+                continue;
+            }
+            match audio_transcoders.get_mut(&ist_index) {
                 Some(transcoder) => {
-                    packet.rescale_ts(stream.time_base(), transcoder.decoder.time_base());
-                    transcoder.send_packet_to_decoder(&packet);
-                    transcoder.receive_and_process_decoded_frames(&mut octx, ost_time_base);
+                    transcoder.send_packet_to_decoder(&packet)?;
+                    transcoder.receive_and_process_decoded_frames(&mut octx, ost_time_base)?;
                 }
                 None => {
-                    // Do stream copy on non-video streams.
+                    // Do stream copy on the audio stream.
                     packet.rescale_ts(ist_time_bases[ist_index], ost_time_base);
                     packet.set_position(-1);
                     packet.set_stream(ost_index as _);
-                    packet.write_interleaved(&mut octx).unwrap();
+                    copied_bytes += packet.size() as u64;
+                    packet.write_interleaved(&mut octx)?;
                 }
             }
         }
 
         // Flush encoders and decoders.
-        for (ost_index, transcoder) in transcoders.iter_mut() {
-            let ost_time_base = ost_time_bases[*ost_index];
-            transcoder.send_eof_to_decoder();
-            transcoder.receive_and_process_decoded_frames(&mut octx, ost_time_base);
-            transcoder.send_eof_to_encoder();
-            transcoder.receive_and_process_encoded_packets(&mut octx, ost_time_base);
+        for (ist_index, transcoder) in transcoders.iter_mut() {
+            let ost_time_base = ost_time_bases[stream_mapping[*ist_index] as usize];
+            transcoder.send_eof_to_decoder()?;
+            transcoder.receive_and_process_decoded_frames(&mut octx, ost_time_base)?;
+            transcoder.send_eof_to_encoder()?;
+            transcoder.receive_and_process_encoded_packets(&mut octx, ost_time_base)?;
+        }
+        for (ist_index, transcoder) in audio_transcoders.iter_mut() {
+            let ost_time_base = ost_time_bases[stream_mapping[*ist_index] as usize];
+            transcoder.send_eof_to_decoder()?;
+            transcoder.receive_and_process_decoded_frames(&mut octx, ost_time_base)?;
+            transcoder.flush_filter()?;
+            transcoder.receive_and_process_filtered_frames(&mut octx, ost_time_base)?;
+            transcoder.send_eof_to_encoder()?;
+            transcoder.receive_and_process_encoded_packets(&mut octx, ost_time_base)?;
         }
 
-        octx.write_trailer().unwrap();
-        // spawn ffmpeg command
-        /*format::context::input::dump(&ictx, 0, Some(&file_path));
-        let mut cmd = std::process::Command::new("ffmpeg");
-        cmd.arg("-re")
-            .arg("-i")
-            .arg(file_path)
-            .arg("-c:v")
-            .arg("copy")
-            .arg("-c:a")
-            .arg("copy")
-            .arg("-f")
-            .arg("flv")
-            .arg(format!(
-                "{}/{}",
-                self.client.config.rtmp_endpoint, stream_key
-            ));
-        println!("{:?}", cmd);
-        let output = cmd.output().expect("failed to execute process");
-        println!("status: {}", output.status);
-        println!("stdout: {}", String::from_utf8_lossy(&output.stdout));*/
+        octx.write_trailer()?;
+        Ok(())
+    };
+    let result = send();
+    // A last report with where the push got to, so a reconnect resumes from exactly there
+    let (frames, dropped, bytes) = push_totals(&transcoders, &audio_transcoders, copied_bytes);
+    control.report(meter.measure(frames, dropped, bytes, out_time));
+    result
+}
+
+/// Frames encoded, frames dropped and bytes sent so far by a push
+fn push_totals(
+    transcoders: &HashMap<usize, Transcoder>,
+    audio_transcoders: &HashMap<usize, AudioTranscoder>,
+    copied_bytes: u64,
+) -> (u64, u64, u64) {
+    let frames = transcoders.values().map(|t| t.encoded_frames).sum();
+    let dropped = transcoders.values().map(|t| t.dropped_frames).sum();
+    let bytes = copied_bytes
+        + transcoders.values().map(|t| t.encoded_bytes).sum::<u64>()
+        + audio_transcoders.values().map(|t| t.encoded_bytes).sum::<u64>();
+    (frames, dropped, bytes)
+}
+
+/// The output index of each input stream, -1 for dropped streams
+///
+/// Ingest takes one video and one audio track, so only the best of each is kept, in input order.
+pub(crate) fn map_streams(
+    stream_count: usize,
+    best_video: Option<usize>,
+    best_audio: Option<usize>,
+) -> Vec<isize> {
+    let mut ost_index = 0;
+    (0..stream_count)
+        .map(|ist_index| {
+            if Some(ist_index) != best_video && Some(ist_index) != best_audio {
+                return -1;
+            }
+            ost_index += 1;
+            ost_index - 1
+        })
+        .collect()
+}
+
+/// Transcode a file in realtime into numbered MPEG-TS segments, `0.ts`, `1.ts`...
+///
+/// Segments are cut on the first keyframe after `segment_duration` seconds. Each one is added to
+/// the CSV list at `list`, as `<file name>,<start>,<end>`, once it is complete.
+pub(crate) fn segment_file(
+    file_path: &String,
+    dir: &std::path::Path,
+    list: &std::path::Path,
+    segment_duration: f64,
+    options: &PushOptions,
+    control: &PushControl,
+) -> Result<(), errors::Error> {
+    ffmpeg_next::init()?;
+    let mut ictx = format::input(&file_path)?;
+    let mut muxer_options = Dictionary::new();
+    muxer_options.set("segment_format", "mpegts");
+    muxer_options.set("segment_time", &segment_duration.to_string());
+    muxer_options.set("segment_list", &list.to_string_lossy());
+    muxer_options.set("segment_list_type", "csv");
+    // Keep timestamps running across segments, like a live stream
+    muxer_options.set("reset_timestamps", "0");
+    let pattern = dir.join("%d.ts").to_string_lossy().to_string();
+    push_input_with(&mut ictx, &pattern, "segment", muxer_options, options, control)
+}
+
+/// Push a generated pattern and tone to an ingest URL in realtime
+pub(crate) fn push_pattern(
+    pattern: &TestPattern,
+    output_url: &String,
+    output_format: &str,
+    options: &PushOptions,
+    control: &PushControl,
+) -> Result<(), errors::Error> {
+    ffmpeg_next::init()?;
+    let mut octx = format::output_as(output_url, output_format)?;
+
+    let source = VideoSource {
+        width: pattern.width,
+        height: pattern.height,
+        format: format::Pixel::YUV420P,
+        aspect_ratio: Rational(1, 1),
+        frame_rate: Rational(pattern.fps as i32, 1),
+    };
+    let mut video = Transcoder::from_frames(&source, &mut octx, 0, options)?;
+    let sample_rate = pattern.sample_rate as i32;
+    let mut audio =
+        AudioTranscoder::from_frames(&mut octx, 1, sample_rate, ChannelLayout::STEREO, options)?;
+    let sample_format = format::Sample::F32(format::sample::Type::Planar);
+    if audio.encoder.format() != sample_format {
+        return Err(errors::Error::FFMPEG(String::from(
+            "the AAC encoder doesn't take planar float samples",
+        )));
     }
+    let frame_size = (audio.encoder.frame_size() as usize).max(1);
 
-    /// Temp ffmpeg command spawn. TODO: Replace with a proper library.
-    pub fn push_to_region(
-        self: &Self,
-        stream_key: &String,
-        file_path: &String,
-        region: &String,
-        ffmpeg_path: &String,
-        proc_id: &mut Option<String>,
-    ) -> Result<String, String> {
-        let mut _region_url = String::new();
+    octx.write_header()?;
+    control.connected();
+    let video_time_base = octx.stream(0).unwrap().time_base();
+    let audio_time_base = octx.stream(1).unwrap().time_base();
 
-        let mut pid = String::new();
+    let mut pacer = Pacer::new();
+    let mut meter = ProgressMeter::new();
+    let mut samples_sent: u64 = 0;
+    let mut index: u64 = 0;
+    while pattern.frame_count().map_or(true, |count| index < count) && !control.is_stopped() {
+        let timestamp = index as f64 / pattern.fps as f64;
+        pacer.wait(timestamp);
 
-        if proc_id.is_none() {
-            pid = format!("{:x}", rand::random::<u32>())
-        } else {
-            pid = proc_id.clone().unwrap();
+        let image = pattern.render(index);
+        let mut frame = frame::Video::new(format::Pixel::YUV420P, image.width, image.height);
+        let chroma_width = image.width.div_ceil(2) as usize;
+        let chroma_height = image.height.div_ceil(2) as usize;
+        for (plane, data, width, height) in [
+            (0, &image.y, image.width as usize, image.height as usize),
+            (1, &image.u, chroma_width, chroma_height),
+            (2, &image.v, chroma_width, chroma_height),
+        ] {
+            let stride = frame.stride(plane);
+            let dst = frame.data_mut(plane);
+            for row in 0..height {
+                dst[row * stride..row * stride + width]
+                    .copy_from_slice(&data[row * width..(row + 1) * width]);
+            }
         }
+        video.encode_frame(&mut frame, timestamp, &mut octx, video_time_base)?;
 
-        let title = pid;
-
-        if region != "none" {
-            _region_url = self
-                .client
-                .config
-                .rtmp_endpoint
-                .replace("rtmp://", &format!("rtmp://{}-", region));
-        } else {
-            _region_url = self.client.config.rtmp_endpoint.to_string();
+        // Keep the tone level with the video
+        let until = ((index + 1) as f64 / pattern.fps as f64 * sample_rate as f64) as u64;
+        while samples_sent < until {
+            let tone = pattern.tone(samples_sent, frame_size);
+            let mut frame = frame::Audio::new(sample_format, frame_size, ChannelLayout::STEREO);
+            frame.set_rate(pattern.sample_rate);
+            for channel in 0..2 {
+                frame.plane_mut::<f32>(channel).copy_from_slice(&tone);
+            }
+            audio.encode_frame(&mut frame, &mut octx, audio_time_base)?;
+            samples_sent += frame_size as u64;
         }
 
-        // spawn ffmpeg command
-        let mut cmd = std::process::Command::new(ffmpeg_path);
-        cmd.arg("-re")
-            .arg("-i")
-            .arg(file_path)
-            .arg("-metadata")
-            .arg(format!("title={}", title))
-            .arg("-c:v")
-            .arg("copy")
-            .arg("-c:a")
-            .arg("copy")
-            .arg("-f")
-            .arg("flv")
-            .arg(format!("{}/{}", _region_url, stream_key))
-            .stdout(std::process::Stdio::inherit())
-            .stderr(std::process::Stdio::inherit());
-        let output = cmd.output().expect("failed to execute process");
-        let stderr = String::from_utf8_lossy(&output.stderr);
-
-        if output.status.to_string() != "exit status: 0" {
-            return Err(output.status.to_string());
-        } else {
-            return Ok(output.status.to_string());
+        if meter.is_due() {
+            control.report(meter.measure(
+                video.encoded_frames,
+                video.dropped_frames,
+                video.encoded_bytes + audio.encoded_bytes,
+                timestamp,
+            ));
         }
+        index += 1;
     }
+
+    video.send_eof_to_encoder()?;
+    video.receive_and_process_encoded_packets(&mut octx, video_time_base)?;
+    audio.send_eof_to_encoder()?;
+    audio.receive_and_process_encoded_packets(&mut octx, audio_time_base)?;
+    octx.write_trailer()?;
+    Ok(())
 }
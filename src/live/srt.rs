@@ -0,0 +1,158 @@
+#[derive(Debug, Clone)]
+pub struct Srt {
+    pub client: crate::LivepeerClient,
+}
+
+use crate::errors;
+use crate::live::handle::{PushControl, PushHandle};
+use crate::live::options::{PushOptions, SrtOptions};
+use crate::live::pattern::TestPattern;
+use crate::live::rtmp::{push_file, push_pattern};
+
+/// SRT carries MPEG-TS
+const OUTPUT_FORMAT: &str = "mpegts";
+
+/// Live SRT Methods
+///
+impl Srt {
+    /// The SRT ingest URL for a stream key
+    ///
+    /// The ingest is on the same host as the RTMP one, e.g.
+    /// `srt://rtmp.livepeer.com:2935?streamid=<stream_key>`.
+    ///
+    /// # Parameters
+    /// * `stream_key` - The stream key
+    /// * `srt_options` - Latency and encryption settings
+    ///
+    /// # Returns
+    /// * `String` - The URL, with the settings as query parameters for ffmpeg
+    pub fn url(self: &Self, stream_key: &String, srt_options: &SrtOptions) -> String {
+        let host = self
+            .client
+            .config
+            .rtmp_endpoint
+            .trim_start_matches("rtmp://")
+            .split(['/', ':'])
+            .next()
+            .unwrap_or_default();
+        // ffmpeg takes the latency in microseconds
+        let mut url = format!(
+            "srt://{}:{}?streamid={}&mode=caller&latency={}",
+            host,
+            srt_options.port,
+            stream_key,
+            srt_options.latency.as_micros()
+        );
+        if let Some(passphrase) = &srt_options.passphrase {
+            url.push_str(&format!("&passphrase={}", passphrase));
+        }
+        if let Some(key_length) = srt_options.key_length {
+            url.push_str(&format!("&pbkeylen={}", key_length));
+        }
+        url
+    }
+
+    /// Push a file to the SRT ingest
+    ///
+    /// Encoded like `Rtmp::push_with_options`, but muxed as MPEG-TS.
+    ///
+    /// # Parameters
+    /// * `stream_key` - The stream key
+    /// * `file_path` - The file to push
+    /// * `options` - Encoder settings, validated before anything is sent
+    /// * `srt_options` - Latency and encryption settings, validated before anything is sent
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result once the whole file was sent, or an error
+    pub fn push(
+        self: &Self,
+        stream_key: &String,
+        file_path: &String,
+        options: &PushOptions,
+        srt_options: &SrtOptions,
+    ) -> Result<(), errors::Error> {
+        options.validate()?;
+        srt_options.validate()?;
+        let output_url = self.url(stream_key, srt_options);
+        push_file(file_path, &output_url, OUTPUT_FORMAT, options, &PushControl::detached())
+    }
+
+    /// Push a file to the SRT ingest in the background
+    ///
+    /// # Parameters
+    /// * `stream_key` - The stream key
+    /// * `file_path` - The file to push
+    /// * `options` - Encoder settings, validated before the push starts
+    /// * `srt_options` - Latency and encryption settings, validated before the push starts
+    ///
+    /// # Returns
+    /// * `Result<PushHandle, errors::Error>` - A handle to stop, wait on or follow the push, or a `PUSHOPTIONS` error
+    pub fn spawn(
+        self: &Self,
+        stream_key: &String,
+        file_path: &String,
+        options: &PushOptions,
+        srt_options: &SrtOptions,
+    ) -> Result<PushHandle, errors::Error> {
+        options.validate()?;
+        srt_options.validate()?;
+        let output_url = self.url(stream_key, srt_options);
+        let file_path = file_path.clone();
+        let options = options.clone();
+        Ok(PushHandle::spawn_thread(stream_key, move |control| {
+            push_file(&file_path, &output_url, OUTPUT_FORMAT, &options, &control)
+        }))
+    }
+
+    /// Push a generated test pattern and tone to the SRT ingest
+    ///
+    /// # Parameters
+    /// * `stream_key` - The stream key
+    /// * `pattern` - The pattern to generate
+    /// * `options` - Encoder settings, validated before anything is sent
+    /// * `srt_options` - Latency and encryption settings, validated before anything is sent
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result once the pattern's duration was sent, or an error
+    pub fn push_test_pattern(
+        self: &Self,
+        stream_key: &String,
+        pattern: &TestPattern,
+        options: &PushOptions,
+        srt_options: &SrtOptions,
+    ) -> Result<(), errors::Error> {
+        options.validate()?;
+        srt_options.validate()?;
+        pattern.validate()?;
+        let output_url = self.url(stream_key, srt_options);
+        push_pattern(pattern, &output_url, OUTPUT_FORMAT, options, &PushControl::detached())
+    }
+
+    /// Push a generated test pattern and tone to the SRT ingest, in the background
+    ///
+    /// # Parameters
+    /// * `stream_key` - The stream key
+    /// * `pattern` - The pattern to generate, with no duration it runs until stopped
+    /// * `options` - Encoder settings, validated before the push starts
+    /// * `srt_options` - Latency and encryption settings, validated before the push starts
+    ///
+    /// # Returns
+    /// * `Result<PushHandle, errors::Error>` - A handle to the push, or a `PUSHOPTIONS` error
+    pub fn spawn_test_pattern(
+        self: &Self,
+        stream_key: &String,
+        pattern: &TestPattern,
+        options: &PushOptions,
+        srt_options: &SrtOptions,
+    ) -> Result<PushHandle, errors::Error> {
+        options.validate()?;
+        srt_options.validate()?;
+        pattern.validate()?;
+        let output_url = self.url(stream_key, srt_options);
+        let pattern = pattern.clone();
+        let options = options.clone();
+        Ok(PushHandle::spawn_thread(stream_key, move |control| {
+            push_pattern(&pattern, &output_url, OUTPUT_FORMAT, &options, &control)
+        }))
+    }
+}
@@ -70,10 +70,21 @@ impl crate::live::Stream for Stream {
         &self,
         name: &String,
         profiles: &Vec<crate::data::stream::Profile>,
-        playback_policy: Option<serde_json::Value>,
+        playback_policy: Option<crate::data::stream::PlaybackPolicy>,
     ) -> Result<String, errors::Error> {
         self.clone().create_stream(name, profiles, playback_policy)
     }
+
+    /// Delete a stream
+    ///
+    /// # Parameters
+    /// * `stream_id` - The ID of the stream
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result or an error
+    fn delete_stream(&self, stream_id: String) -> Result<(), errors::Error> {
+        self.clone().delete_stream(stream_id)
+    }
 }
 
 impl Stream {
@@ -189,30 +200,48 @@ impl Stream {
     /// * `playback_policy` - An optional playback policy
     ///
     /// # Returns
-    /// * `Result<String, errors::Error>` - The ID of the created stream or an error
+    /// * `Result<String, errors::Error>` - The bare ID of the created stream, or a `CREATESTREAM` error if the request failed or the response has no ID
     pub fn create_stream(
         self: Self,
         name: &String,
         profiles: &Vec<crate::data::stream::Profile>,
-        playback_policy: Option<serde_json::Value>,
+        playback_policy: Option<crate::data::stream::PlaybackPolicy>,
     ) -> Result<String, errors::Error> {
         let mut result: Result<String, errors::Error> = Err(errors::Error::CREATESTREAM);
-        let mut stream_id: String = "".to_string();
         let mut data = serde_json::json!({
             "name": name,
-            "playbackPolicy": playback_policy.unwrap(),
             //"profiles": profiles,
         });
+        if let Some(policy) = playback_policy {
+            data["playbackPolicy"] = serde_json::json!(policy);
+        }
         let res: Result<serde_json::Value, errors::Error> = crate::utils::SurfRequest::post(
             format!("{}{}", self.client.config.host, "/api/stream"),
             serde_json::to_string(&data).unwrap(),
             self.client,
         );
         if res.is_ok() {
-            let stream: serde_json::Value = serde_json::from_value(res.unwrap()).unwrap();
-            stream_id = stream["id"].to_string();
-            result = Ok(stream_id)
+            let stream: serde_json::Value = res.unwrap();
+            // A response without a string ID didn't create a usable stream
+            if let Some(stream_id) = stream["id"].as_str() {
+                result = Ok(stream_id.to_string())
+            }
         }
         result
     }
+
+    /// Delete a stream
+    ///
+    /// # Parameters
+    /// * `stream_id` - The ID of the stream
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result or an error
+    pub fn delete_stream(self: Self, stream_id: String) -> Result<(), errors::Error> {
+        crate::utils::SurfRequest::delete(
+            format!("{}{}/{}", self.client.config.host, "/api/stream", stream_id),
+            self.client,
+        )
+        .map(|_| ())
+    }
 }
@@ -0,0 +1,211 @@
+use crate::errors;
+use crate::live::handle::{PushControl, PushEvent};
+use std::time::{Duration, Instant};
+
+/// How often a backoff wait checks for a stop request
+const STOP_POLL: Duration = Duration::from_millis(100);
+
+/// Exponential backoff between reconnect attempts
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+    pub multiplier: f64,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(30),
+            multiplier: 2.0,
+        }
+    }
+}
+
+impl Backoff {
+    /// The wait before a reconnect
+    ///
+    /// # Parameters
+    /// * `failures` - Consecutive failed attempts so far, counting from 1
+    ///
+    /// # Returns
+    /// * `Duration` - The delay, capped at `max`
+    pub fn delay(&self, failures: u32) -> Duration {
+        let factor = self.multiplier.max(1.0).powi(failures.saturating_sub(1) as i32);
+        self.initial.mul_f64(factor).min(self.max)
+    }
+}
+
+/// Where and how often a supervised push reconnects
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailoverPolicy {
+    /// Ingest endpoints in order of preference, without the stream key
+    pub endpoints: Vec<String>,
+    /// Failed attempts on an endpoint before moving to the next one
+    pub attempts_per_endpoint: u32,
+    /// Failed attempts in a row before giving up, retrying forever when `None`
+    pub max_failures: Option<u32>,
+    /// Media time a connection must send to count as established, shorter runs count as failures
+    pub min_healthy_run: Duration,
+    pub backoff: Backoff,
+}
+
+impl FailoverPolicy {
+    pub fn new(endpoints: Vec<String>) -> Self {
+        FailoverPolicy {
+            endpoints,
+            attempts_per_endpoint: 3,
+            max_failures: Some(12),
+            min_healthy_run: Duration::from_secs(10),
+            backoff: Backoff::default(),
+        }
+    }
+
+    pub fn with_attempts_per_endpoint(mut self, attempts: u32) -> Self {
+        self.attempts_per_endpoint = attempts;
+        self
+    }
+
+    pub fn with_max_failures(mut self, max_failures: Option<u32>) -> Self {
+        self.max_failures = max_failures;
+        self
+    }
+
+    pub fn with_min_healthy_run(mut self, min_healthy_run: Duration) -> Self {
+        self.min_healthy_run = min_healthy_run;
+        self
+    }
+
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Check the policy can be followed
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result or a `PUSHOPTIONS` error describing the problem
+    pub fn validate(&self) -> Result<(), errors::Error> {
+        if self.endpoints.is_empty() {
+            return Err(errors::Error::PUSHOPTIONS(String::from(
+                "failover needs at least one endpoint",
+            )));
+        }
+        if self.attempts_per_endpoint == 0 {
+            return Err(errors::Error::PUSHOPTIONS(String::from(
+                "attempts per endpoint must not be zero",
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Tracks failures to pick the endpoint and delay for the next attempt
+#[derive(Debug)]
+pub(crate) struct Failover<'a> {
+    policy: &'a FailoverPolicy,
+    endpoint: usize,
+    /// Failures in a row, across endpoints
+    failures: u32,
+    /// Failures in a row on the current endpoint
+    endpoint_failures: u32,
+}
+
+impl<'a> Failover<'a> {
+    pub(crate) fn new(policy: &'a FailoverPolicy) -> Self {
+        Failover {
+            policy,
+            endpoint: 0,
+            failures: 0,
+            endpoint_failures: 0,
+        }
+    }
+
+    pub(crate) fn endpoint(&self) -> &'a String {
+        &self.policy.endpoints[self.endpoint]
+    }
+
+    /// Record a dropped or refused connection
+    ///
+    /// A connection that sent at least `min_healthy_run` of media was established, so it
+    /// reconnects to the same endpoint straight away. Anything shorter, including an ingest that
+    /// takes a few packets and drops the connection, is a failure: it backs off and, after
+    /// `attempts_per_endpoint` failures, moves on to the next endpoint, wrapping around after the last.
+    ///
+    /// # Parameters
+    /// * `sent` - The media time sent before the connection ended
+    ///
+    /// # Returns
+    /// * `Option<Duration>` - The wait before the next attempt, `None` once out of attempts
+    pub(crate) fn record(&mut self, sent: Duration) -> Option<Duration> {
+        if !sent.is_zero() && sent >= self.policy.min_healthy_run {
+            self.failures = 0;
+            self.endpoint_failures = 0;
+            return Some(Duration::ZERO);
+        }
+        self.failures += 1;
+        self.endpoint_failures += 1;
+        if self.policy.max_failures.map_or(false, |max| self.failures >= max) {
+            return None;
+        }
+        if self.endpoint_failures >= self.policy.attempts_per_endpoint {
+            self.endpoint = (self.endpoint + 1) % self.policy.endpoints.len();
+            self.endpoint_failures = 0;
+        }
+        Some(self.policy.backoff.delay(self.failures))
+    }
+}
+
+/// Run a push, reconnecting and failing over until it completes, is stopped or runs out of attempts
+///
+/// `push` is called with the ingest URL and the media time to resume from, in seconds. It must
+/// report its progress on the way out, whether it completed or failed, so the next attempt
+/// resumes where it stopped.
+pub(crate) fn supervise<F>(
+    stream_key: &str,
+    policy: &FailoverPolicy,
+    control: &PushControl,
+    mut push: F,
+) -> Result<(), errors::Error>
+where
+    F: FnMut(&String, f64, &PushControl) -> Result<(), errors::Error>,
+{
+    let mut failover = Failover::new(policy);
+    let mut resume_at = 0.0;
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let output_url = format!("{}/{}", failover.endpoint(), stream_key);
+        control.take_progress();
+        let result = push(&output_url, resume_at, control);
+        // The last report is the one sent as the push ended
+        let sent = control
+            .take_progress()
+            .map_or(Duration::ZERO, |progress| progress.out_time);
+        resume_at += sent.as_secs_f64();
+        let error = match result {
+            Ok(()) => return Ok(()),
+            Err(_) if control.is_stopped() => return Ok(()),
+            Err(e) => e,
+        };
+
+        let delay = match failover.record(sent) {
+            Some(delay) => delay,
+            None => return Err(error),
+        };
+        control.send(PushEvent::Reconnecting {
+            attempt,
+            endpoint: failover.endpoint().clone(),
+            delay,
+            error: format!("{:?}", error),
+        });
+        let deadline = Instant::now() + delay;
+        while Instant::now() < deadline {
+            if control.is_stopped() {
+                return Ok(());
+            }
+            std::thread::sleep(STOP_POLL.min(deadline - Instant::now()));
+        }
+    }
+}
@@ -0,0 +1,512 @@
+#[derive(Debug, Clone)]
+pub struct Whip {
+    pub client: crate::LivepeerClient,
+}
+
+use crate::errors;
+use crate::live::options::WhipOptions;
+use crate::utils::SurfRequest;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use webrtc::api::interceptor_registry::register_default_interceptors;
+use webrtc::api::media_engine::{MediaEngine, MIME_TYPE_H264, MIME_TYPE_OPUS};
+use webrtc::api::{APIBuilder, API};
+use webrtc::ice_transport::ice_server::RTCIceServer;
+use webrtc::interceptor::registry::Registry;
+use webrtc::media::Sample;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::rtp_transceiver::rtp_codec::RTCRtpCodecCapability;
+use webrtc::rtp_transceiver::rtp_transceiver_direction::RTCRtpTransceiverDirection;
+use webrtc::rtp_transceiver::RTCRtpTransceiverInit;
+use webrtc::track::track_local::track_local_static_sample::TrackLocalStaticSample;
+
+/// How long to gather ICE candidates before sending the offer with those found so far
+const GATHERING_TIMEOUT: Duration = Duration::from_secs(5);
+/// Stream ID of the published tracks
+const STREAM_ID: &str = "livepeer-rs";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WhipState {
+    New,
+    Connecting,
+    Connected,
+    /// Connectivity was lost and may come back
+    Disconnected,
+    Failed,
+    Closed,
+}
+
+impl From<RTCPeerConnectionState> for WhipState {
+    fn from(state: RTCPeerConnectionState) -> Self {
+        match state {
+            RTCPeerConnectionState::Connecting => WhipState::Connecting,
+            RTCPeerConnectionState::Connected => WhipState::Connected,
+            RTCPeerConnectionState::Disconnected => WhipState::Disconnected,
+            RTCPeerConnectionState::Failed => WhipState::Failed,
+            RTCPeerConnectionState::Closed => WhipState::Closed,
+            _ => WhipState::New,
+        }
+    }
+}
+
+/// A WebRTC API with the default codecs and RTCP interceptors
+fn webrtc_api() -> Result<API, errors::Error> {
+    let mut media_engine = MediaEngine::default();
+    media_engine.register_default_codecs()?;
+    let registry = register_default_interceptors(Registry::new(), &mut media_engine)?;
+    Ok(APIBuilder::new()
+        .with_media_engine(media_engine)
+        .with_interceptor_registry(registry)
+        .build())
+}
+
+fn tokio_runtime() -> Result<tokio::runtime::Runtime, errors::Error> {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(|_| errors::Error::IO)
+}
+
+/// Set the local description and wait for ICE gathering, returning the SDP with all candidates
+async fn local_sdp_with_candidates(
+    peer_connection: &RTCPeerConnection,
+    description: RTCSessionDescription,
+) -> Result<String, errors::Error> {
+    let mut gathered = peer_connection.gathering_complete_promise().await;
+    peer_connection.set_local_description(description).await?;
+    let _ = tokio::time::timeout(GATHERING_TIMEOUT, gathered.recv()).await;
+    peer_connection
+        .local_description()
+        .await
+        .map(|description| description.sdp)
+        .ok_or(errors::Error::WEBRTC(String::from("no local description")))
+}
+
+/// A WebRTC publish session opened with WHIP
+///
+/// Dropping a session closes it and deletes it on the server.
+pub struct WhipSession {
+    /// The WHIP resource of the session, from the `Location` header of the answer
+    pub resource_url: Option<String>,
+    runtime: tokio::runtime::Runtime,
+    peer_connection: Arc<RTCPeerConnection>,
+    video: Arc<TrackLocalStaticSample>,
+    audio: Option<Arc<TrackLocalStaticSample>>,
+    state: Arc<Mutex<WhipState>>,
+}
+
+impl WhipSession {
+    /// Publish to a WHIP endpoint
+    ///
+    /// All ICE candidates are gathered before the offer is sent, so the endpoint doesn't need to
+    /// support trickle ICE. Returns once ICE and DTLS are connected.
+    ///
+    /// # Parameters
+    /// * `endpoint` - The WHIP endpoint URL
+    /// * `options` - ICE servers, tracks and timeout
+    ///
+    /// # Returns
+    /// * `Result<WhipSession, errors::Error>` - The connected session, or the signalling or `WEBRTC` error
+    pub fn connect(endpoint: &str, options: &WhipOptions) -> Result<WhipSession, errors::Error> {
+        let runtime = tokio_runtime()?;
+        let ice_servers = options
+            .ice_servers
+            .iter()
+            .map(|server| RTCIceServer {
+                urls: server.urls.clone(),
+                username: server.username.clone().unwrap_or_default(),
+                credential: server.credential.clone().unwrap_or_default(),
+                ..Default::default()
+            })
+            .collect();
+
+        let (peer_connection, video, audio) = runtime.block_on(async {
+            let peer_connection = Arc::new(
+                webrtc_api()?
+                    .new_peer_connection(RTCConfiguration {
+                        ice_servers,
+                        ..Default::default()
+                    })
+                    .await?,
+            );
+            let send_only = [RTCRtpTransceiverInit {
+                direction: RTCRtpTransceiverDirection::Sendonly,
+                send_encodings: vec![],
+            }];
+            let video = Arc::new(TrackLocalStaticSample::new(
+                RTCRtpCodecCapability {
+                    mime_type: MIME_TYPE_H264.to_string(),
+                    clock_rate: 90000,
+                    ..Default::default()
+                },
+                String::from("video"),
+                String::from(STREAM_ID),
+            ));
+            peer_connection
+                .add_transceiver_from_track(video.clone(), &send_only)
+                .await?;
+            let audio = if options.audio {
+                let audio = Arc::new(TrackLocalStaticSample::new(
+                    RTCRtpCodecCapability {
+                        mime_type: MIME_TYPE_OPUS.to_string(),
+                        clock_rate: 48000,
+                        channels: 2,
+                        ..Default::default()
+                    },
+                    String::from("audio"),
+                    String::from(STREAM_ID),
+                ));
+                peer_connection
+                    .add_transceiver_from_track(audio.clone(), &send_only)
+                    .await?;
+                Some(audio)
+            } else {
+                None
+            };
+            Ok::<_, errors::Error>((peer_connection, video, audio))
+        })?;
+
+        let state = Arc::new(Mutex::new(WhipState::New));
+        let (changes, states) = channel();
+        let current = state.clone();
+        peer_connection.on_peer_connection_state_change(Box::new(move |change| {
+            let change = WhipState::from(change);
+            *current.lock().unwrap() = change;
+            let _ = changes.send(change);
+            Box::pin(async {})
+        }));
+
+        // From here on dropping the session closes the connection and deletes the resource
+        let mut session = WhipSession {
+            resource_url: None,
+            runtime,
+            peer_connection,
+            video,
+            audio,
+            state,
+        };
+        let offer = session.runtime.block_on(async {
+            let offer = session.peer_connection.create_offer(None).await?;
+            local_sdp_with_candidates(&session.peer_connection, offer).await
+        })?;
+        let (answer, resource_url) =
+            SurfRequest::post_sdp(endpoint.to_string(), offer, options.connect_timeout)?;
+        session.resource_url = resource_url;
+        session.runtime.block_on(
+            session
+                .peer_connection
+                .set_remote_description(RTCSessionDescription::answer(answer)?),
+        )?;
+
+        let deadline = Instant::now() + options.connect_timeout;
+        loop {
+            match states.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(WhipState::Connected) => return Ok(session),
+                Ok(WhipState::Failed) | Ok(WhipState::Closed) => {
+                    return Err(errors::Error::WEBRTC(String::from("ICE connection failed")))
+                }
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => {
+                    return Err(errors::Error::WEBRTC(String::from(
+                        "timed out waiting for the ICE connection",
+                    )))
+                }
+            }
+        }
+    }
+
+    /// The connection state
+    pub fn state(&self) -> WhipState {
+        *self.state.lock().unwrap()
+    }
+
+    /// Send an encoded video frame
+    ///
+    /// # Parameters
+    /// * `data` - An H.264 access unit in Annex B format, with start codes
+    /// * `duration` - How long the frame is shown
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result, or a `WEBRTC` error
+    pub fn write_video(&self, data: &[u8], duration: Duration) -> Result<(), errors::Error> {
+        let sample = Sample {
+            data: Bytes::copy_from_slice(data),
+            duration,
+            ..Default::default()
+        };
+        Ok(self.runtime.block_on(self.video.write_sample(&sample))?)
+    }
+
+    /// Send an encoded audio frame
+    ///
+    /// # Parameters
+    /// * `data` - An Opus packet
+    /// * `duration` - The packet duration, usually 20ms
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result, or a `WEBRTC` error, also when the session was opened without audio
+    pub fn write_audio(&self, data: &[u8], duration: Duration) -> Result<(), errors::Error> {
+        let audio = self
+            .audio
+            .as_ref()
+            .ok_or(errors::Error::WEBRTC(String::from("the session has no audio track")))?;
+        let sample = Sample {
+            data: Bytes::copy_from_slice(data),
+            duration,
+            ..Default::default()
+        };
+        Ok(self.runtime.block_on(audio.write_sample(&sample))?)
+    }
+
+    /// End the session, deleting it on the server
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result, or the error deleting the resource
+    pub fn close(&mut self) -> Result<(), errors::Error> {
+        let deleted = match self.resource_url.take() {
+            Some(resource_url) => SurfRequest::delete_resource(resource_url),
+            None => Ok(()),
+        };
+        self.runtime.block_on(self.peer_connection.close())?;
+        deleted
+    }
+}
+
+impl Drop for WhipSession {
+    fn drop(&mut self) {
+        if self.state() != WhipState::Closed || self.resource_url.is_some() {
+            let _ = self.close();
+        }
+    }
+}
+
+impl std::fmt::Debug for WhipSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WhipSession")
+            .field("resource_url", &self.resource_url)
+            .field("state", &self.state())
+            .finish()
+    }
+}
+
+/// Live WebRTC Methods
+///
+impl Whip {
+    /// The WHIP endpoint for a stream key, e.g. `https://playback.livepeer.studio/webrtc/<stream_key>`
+    pub fn endpoint(self: &Self, stream_key: &String) -> String {
+        format!("{}/{}", self.client.config.whip_endpoint, stream_key)
+    }
+
+    /// Open a WebRTC publish session for a stream key
+    ///
+    /// The endpoint redirects to the closest ingest node, which is looked up first so the offer
+    /// goes straight to it.
+    ///
+    /// # Parameters
+    /// * `stream_key` - The stream key
+    /// * `options` - ICE servers, tracks and timeout
+    ///
+    /// # Returns
+    /// * `Result<WhipSession, errors::Error>` - The connected session to write frames to, or an error
+    pub fn publish(
+        self: &Self,
+        stream_key: &String,
+        options: &WhipOptions,
+    ) -> Result<WhipSession, errors::Error> {
+        let endpoint =
+            SurfRequest::resolve_redirect(self.endpoint(stream_key), options.connect_timeout)?;
+        WhipSession::connect(&endpoint, options)
+    }
+}
+
+/// State shared with the stand-in's request thread
+struct StandIn {
+    runtime: tokio::runtime::Runtime,
+    sessions: Mutex<HashMap<u64, Arc<RTCPeerConnection>>>,
+    stream_keys: Mutex<Vec<String>>,
+    packets: Arc<AtomicU64>,
+    next_id: AtomicU64,
+}
+
+impl StandIn {
+    /// Answer an offer with a receive-only peer that counts the RTP packets it gets
+    fn answer(&self, offer: String) -> Result<(u64, String), errors::Error> {
+        let packets = self.packets.clone();
+        let peer_connection = self.runtime.block_on(async {
+            let peer_connection = Arc::new(
+                webrtc_api()?
+                    .new_peer_connection(RTCConfiguration::default())
+                    .await?,
+            );
+            peer_connection.on_track(Box::new(move |track, _| {
+                let packets = packets.clone();
+                Box::pin(async move {
+                    if let Some(track) = track {
+                        tokio::spawn(async move {
+                            while track.read_rtp().await.is_ok() {
+                                packets.fetch_add(1, Ordering::SeqCst);
+                            }
+                        });
+                    }
+                })
+            }));
+            peer_connection
+                .set_remote_description(RTCSessionDescription::offer(offer)?)
+                .await?;
+            Ok::<_, errors::Error>(peer_connection)
+        })?;
+        let answer = self.runtime.block_on(async {
+            let answer = peer_connection.create_answer(None).await?;
+            local_sdp_with_candidates(&peer_connection, answer).await
+        });
+        let answer = match answer {
+            Ok(answer) => answer,
+            Err(e) => {
+                let _ = self.runtime.block_on(peer_connection.close());
+                return Err(e);
+            }
+        };
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        self.sessions.lock().unwrap().insert(id, peer_connection);
+        Ok((id, answer))
+    }
+
+    fn delete(&self, id: u64) -> bool {
+        match self.sessions.lock().unwrap().remove(&id) {
+            Some(peer_connection) => {
+                let _ = self.runtime.block_on(peer_connection.close());
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// A local WHIP endpoint that accepts publishers, for testing without Livepeer
+///
+/// Offers posted to `/<stream_key>` are answered by a peer that receives the media and counts
+/// its RTP packets, and sessions are deleted with `DELETE /resource/<id>`. Stopped on drop.
+pub struct WhipServer {
+    pub addr: std::net::SocketAddr,
+    server: Arc<tiny_http::Server>,
+    state: Arc<StandIn>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl WhipServer {
+    /// Serve the stand-in on a local address
+    ///
+    /// # Parameters
+    /// * `addr` - The address to listen on, e.g. `127.0.0.1:0`
+    ///
+    /// # Returns
+    /// * `Result<WhipServer, errors::Error>` - A handle to the running server or an error
+    pub fn serve(addr: &str) -> Result<WhipServer, errors::Error> {
+        let server = Arc::new(tiny_http::Server::http(addr).map_err(|_| errors::Error::IO)?);
+        let local_addr = server.server_addr().to_ip().ok_or(errors::Error::IO)?;
+        let state = Arc::new(StandIn {
+            runtime: tokio_runtime()?,
+            sessions: Mutex::new(HashMap::new()),
+            stream_keys: Mutex::new(Vec::new()),
+            packets: Arc::new(AtomicU64::new(0)),
+            next_id: AtomicU64::new(0),
+        });
+
+        let listener = server.clone();
+        let stand_in = state.clone();
+        let thread = std::thread::spawn(move || {
+            for mut request in listener.incoming_requests() {
+                let path = request.url().trim_start_matches('/').to_string();
+                let response = match request.method() {
+                    tiny_http::Method::Post => {
+                        let mut offer = String::new();
+                        let _ = std::io::Read::read_to_string(request.as_reader(), &mut offer);
+                        stand_in.stream_keys.lock().unwrap().push(path);
+                        match stand_in.answer(offer) {
+                            Ok((id, answer)) => tiny_http::Response::from_string(answer)
+                                .with_status_code(201)
+                                .with_header(
+                                    tiny_http::Header::from_bytes("Content-Type", "application/sdp")
+                                        .unwrap(),
+                                )
+                                .with_header(
+                                    tiny_http::Header::from_bytes(
+                                        "Location",
+                                        format!("/resource/{}", id),
+                                    )
+                                    .unwrap(),
+                                ),
+                            Err(_) => tiny_http::Response::from_string("").with_status_code(400),
+                        }
+                    }
+                    tiny_http::Method::Delete => {
+                        let id = path.strip_prefix("resource/").and_then(|id| id.parse().ok());
+                        match id.map(|id| stand_in.delete(id)) {
+                            Some(true) => tiny_http::Response::from_string("").with_status_code(200),
+                            _ => tiny_http::Response::from_string("").with_status_code(404),
+                        }
+                    }
+                    _ => tiny_http::Response::from_string("").with_status_code(405),
+                };
+                let _ = request.respond(response);
+            }
+        });
+
+        Ok(WhipServer {
+            addr: local_addr,
+            server,
+            state,
+            thread: Some(thread),
+        })
+    }
+
+    /// WHIP endpoint URL for a stream key
+    pub fn url(&self, stream_key: &str) -> String {
+        format!("http://{}/{}", self.addr, stream_key)
+    }
+
+    /// Stream keys of the offers received so far
+    pub fn stream_keys(&self) -> Vec<String> {
+        self.state.stream_keys.lock().unwrap().clone()
+    }
+
+    /// Sessions not yet deleted
+    pub fn sessions(&self) -> usize {
+        self.state.sessions.lock().unwrap().len()
+    }
+
+    /// RTP packets received over all sessions
+    pub fn packets_received(&self) -> u64 {
+        self.state.packets.load(Ordering::SeqCst)
+    }
+
+    /// Stop accepting requests and wait for the server thread to exit
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        let ids: Vec<u64> = self.state.sessions.lock().unwrap().keys().copied().collect();
+        for id in ids {
+            self.state.delete(id);
+        }
+    }
+}
+
+impl Drop for WhipServer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
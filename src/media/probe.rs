@@ -0,0 +1,342 @@
+use crate::errors;
+use crate::live::options::{
+    MAX_FPS, MAX_HEIGHT, MAX_KEYFRAME_INTERVAL, MAX_VIDEO_BITRATE, MAX_WIDTH,
+};
+use ffmpeg_next::codec::packet::side_data::Type as SideDataType;
+use ffmpeg_next::{codec, format, media, Rational};
+use std::time::Duration;
+
+/// Containers Livepeer accepts for upload, as ffmpeg demuxer names
+const UPLOAD_CONTAINERS: &[&str] = &["mov", "mp4", "matroska", "webm", "flv", "mpegts", "avi"];
+const UPLOAD_VIDEO_CODECS: &[&str] = &["h264", "hevc", "vp8", "vp9", "av1"];
+const UPLOAD_AUDIO_CODECS: &[&str] = &["aac", "mp3", "opus", "vorbis", "ac3"];
+/// How far into the file keyframes are looked for, in seconds
+const KEYFRAME_SCAN: f64 = 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StreamKind {
+    Video,
+    Audio,
+    Subtitle,
+    Data,
+    Attachment,
+    Unknown,
+}
+
+/// What a file is going to be used for, which decides what it is checked against
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProbeTarget {
+    /// `upload_asset`, where Livepeer decodes the file as is
+    Upload,
+    /// `Rtmp::push` and the other pushes, which re-encode video to H.264 and audio to AAC
+    Ingest,
+}
+
+/// A reason a file may fail or play back badly
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProbeWarning {
+    NoVideo,
+    /// Only the first video stream is used, the others are dropped
+    ExtraVideoStreams(usize),
+    /// The container doesn't report a duration, so progress can't be tracked
+    UnknownDuration,
+    UnsupportedContainer(String),
+    UnsupportedVideoCodec(String),
+    UnsupportedAudioCodec(String),
+    /// Larger than the biggest frame ingest accepts, set a size in `PushOptions` to scale it down
+    Resolution {
+        width: u32,
+        height: u32,
+    },
+    /// H.264 needs even dimensions, set a size in `PushOptions`
+    OddDimensions {
+        width: u32,
+        height: u32,
+    },
+    FrameRate(f64),
+    /// Bits per second
+    Bitrate(u64),
+    /// Seconds, Livepeer segments on keyframes so long gaps delay playback
+    KeyframeInterval(f64),
+    /// Degrees clockwise. Pushes don't apply rotation, so the picture arrives sideways
+    Rotated(i32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamInfo {
+    pub index: usize,
+    pub kind: StreamKind,
+    /// The codec as ffmpeg names it, e.g. `h264` or `aac`
+    pub codec: String,
+    /// Bits per second, when the stream reports it
+    pub bitrate: Option<u64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fps: Option<f64>,
+    /// Degrees clockwise the picture is shown rotated by, 0, 90, 180 or 270
+    pub rotation: i32,
+    /// The longest gap between keyframes in the first minute, in seconds
+    pub keyframe_interval: Option<f64>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+}
+
+/// What `probe` found in a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaProbe {
+    /// The ffmpeg demuxer names, e.g. `mov,mp4,m4a,3gp,3g2,mj2`
+    pub container: String,
+    pub duration: Option<Duration>,
+    /// Overall bits per second
+    pub bitrate: Option<u64>,
+    pub streams: Vec<StreamInfo>,
+}
+
+impl MediaProbe {
+    /// The first video stream
+    pub fn video(&self) -> Option<&StreamInfo> {
+        self.streams.iter().find(|s| s.kind == StreamKind::Video)
+    }
+
+    /// The first audio stream
+    pub fn audio(&self) -> Option<&StreamInfo> {
+        self.streams.iter().find(|s| s.kind == StreamKind::Audio)
+    }
+
+    /// Check the file against Livepeer's upload or ingest constraints
+    ///
+    /// # Parameters
+    /// * `target` - Whether the file is going to be uploaded or pushed
+    ///
+    /// # Returns
+    /// * `Vec<ProbeWarning>` - Everything likely to fail or play back badly, empty when the file looks fine
+    pub fn compatibility(&self, target: ProbeTarget) -> Vec<ProbeWarning> {
+        let upload = target == ProbeTarget::Upload;
+        let mut warnings = Vec::new();
+
+        if upload {
+            if !self
+                .container
+                .split(',')
+                .any(|name| UPLOAD_CONTAINERS.contains(&name))
+            {
+                warnings.push(ProbeWarning::UnsupportedContainer(self.container.clone()));
+            }
+            if self.duration.map_or(true, |d| d.is_zero()) {
+                warnings.push(ProbeWarning::UnknownDuration);
+            }
+            if let Some(audio) = self.audio() {
+                if !UPLOAD_AUDIO_CODECS.contains(&audio.codec.as_str()) {
+                    warnings.push(ProbeWarning::UnsupportedAudioCodec(audio.codec.clone()));
+                }
+            }
+        }
+
+        let video = match self.video() {
+            Some(video) => video,
+            None => {
+                warnings.push(ProbeWarning::NoVideo);
+                return warnings;
+            }
+        };
+        let extra = self
+            .streams
+            .iter()
+            .filter(|s| s.kind == StreamKind::Video)
+            .count()
+            - 1;
+        if extra > 0 {
+            warnings.push(ProbeWarning::ExtraVideoStreams(extra));
+        }
+        if upload && !UPLOAD_VIDEO_CODECS.contains(&video.codec.as_str()) {
+            warnings.push(ProbeWarning::UnsupportedVideoCodec(video.codec.clone()));
+        }
+        if let (Some(width), Some(height)) = (video.width, video.height) {
+            // A portrait frame fits once its sides are swapped
+            let (long, short) = (width.max(height), width.min(height));
+            if long > MAX_WIDTH || short > MAX_HEIGHT {
+                warnings.push(ProbeWarning::Resolution { width, height });
+            }
+            if !upload && (width % 2 != 0 || height % 2 != 0) {
+                warnings.push(ProbeWarning::OddDimensions { width, height });
+            }
+        }
+        if let Some(fps) = video.fps {
+            if fps > MAX_FPS as f64 + 0.01 {
+                warnings.push(ProbeWarning::FrameRate(fps));
+            }
+        }
+        if upload {
+            if let Some(bitrate) = video.bitrate.or(self.bitrate) {
+                if bitrate > MAX_VIDEO_BITRATE as u64 {
+                    warnings.push(ProbeWarning::Bitrate(bitrate));
+                }
+            }
+            if let Some(interval) = video.keyframe_interval {
+                if interval > MAX_KEYFRAME_INTERVAL {
+                    warnings.push(ProbeWarning::KeyframeInterval(interval));
+                }
+            }
+        } else if video.rotation != 0 {
+            warnings.push(ProbeWarning::Rotated(video.rotation));
+        }
+        warnings
+    }
+}
+
+/// Read a file's container, streams and codecs, for checking it before an upload or push
+///
+/// The first minute of video packets is read to measure the keyframe interval.
+///
+/// # Parameters
+/// * `file_path` - The file to probe
+///
+/// # Returns
+/// * `Result<MediaProbe, errors::Error>` - What was found, or an `FFMPEG` error if the file can't be read
+pub fn probe(file_path: &str) -> Result<MediaProbe, errors::Error> {
+    ffmpeg_next::init()?;
+    let mut ictx = format::input(file_path)?;
+
+    let positive = |value: i64| (value > 0).then(|| value as u64);
+    let mut streams = Vec::new();
+    for stream in ictx.streams() {
+        let parameters = stream.parameters();
+        let mut info = StreamInfo {
+            index: stream.index(),
+            kind: match parameters.medium() {
+                media::Type::Video => StreamKind::Video,
+                media::Type::Audio => StreamKind::Audio,
+                media::Type::Subtitle => StreamKind::Subtitle,
+                media::Type::Data => StreamKind::Data,
+                media::Type::Attachment => StreamKind::Attachment,
+                media::Type::Unknown => StreamKind::Unknown,
+            },
+            codec: parameters.id().name().to_string(),
+            bitrate: None,
+            width: None,
+            height: None,
+            fps: None,
+            rotation: 0,
+            keyframe_interval: None,
+            sample_rate: None,
+            channels: None,
+        };
+        let context = codec::context::Context::from_parameters(parameters)?;
+        match info.kind {
+            StreamKind::Video => {
+                if let Ok(decoder) = context.decoder().video() {
+                    info.width = Some(decoder.width());
+                    info.height = Some(decoder.height());
+                    info.bitrate = positive(decoder.bit_rate() as i64);
+                }
+                info.fps = [stream.avg_frame_rate(), stream.rate()]
+                    .into_iter()
+                    .find(|rate| rate.numerator() > 0 && rate.denominator() > 0)
+                    .map(f64::from);
+                info.rotation = stream
+                    .side_data()
+                    .find(|side_data| side_data.kind() == SideDataType::DisplayMatrix)
+                    .map(|side_data| display_rotation(side_data.data()))
+                    .or_else(|| stream.metadata().get("rotate").and_then(|r| r.parse().ok()))
+                    .unwrap_or(0)
+                    .rem_euclid(360);
+            }
+            StreamKind::Audio => {
+                if let Ok(decoder) = context.decoder().audio() {
+                    info.sample_rate = Some(decoder.rate());
+                    info.channels = Some(decoder.channels());
+                    info.bitrate = positive(decoder.bit_rate() as i64);
+                }
+            }
+            _ => {}
+        }
+        streams.push(info);
+    }
+
+    let duration = positive(ictx.duration())
+        .map(|d| Duration::from_secs_f64(d as f64 / ffmpeg_next::ffi::AV_TIME_BASE as f64));
+    let mut probe = MediaProbe {
+        container: ictx.format().name().to_string(),
+        duration,
+        bitrate: positive(ictx.bit_rate()),
+        streams,
+    };
+
+    if let Some(video) = probe.video().map(|video| video.index) {
+        let time_base = ictx
+            .stream(video)
+            .map(|stream| stream.time_base())
+            .unwrap_or(Rational(1, ffmpeg_next::ffi::AV_TIME_BASE));
+        let seconds = |pts: i64| pts as f64 * f64::from(time_base);
+        let mut keyframes = Vec::new();
+        let mut end = 0.0;
+        for (stream, packet) in ictx.packets() {
+            if stream.index() != video {
+                continue;
+            }
+            let pts = match packet.pts() {
+                Some(pts) => seconds(pts),
+                None => continue,
+            };
+            if keyframes
+                .first()
+                .map_or(false, |first| pts - first > KEYFRAME_SCAN)
+            {
+                break;
+            }
+            if packet.is_key() {
+                keyframes.push(pts);
+            }
+            end = f64::max(end, pts);
+        }
+        probe.streams[video].keyframe_interval = keyframe_interval(&keyframes, end);
+    }
+    Ok(probe)
+}
+
+/// The rotation stored in a display matrix side data, in whole degrees clockwise
+///
+/// # Parameters
+/// * `matrix` - The 3x3 matrix of 32-bit fixed point values, as stored by ffmpeg
+///
+/// # Returns
+/// * `i32` - The rotation, 0 for a malformed matrix
+pub fn display_rotation(matrix: &[u8]) -> i32 {
+    if matrix.len() < 36 {
+        return 0;
+    }
+    let value = |i: usize| {
+        let bytes = [
+            matrix[i * 4],
+            matrix[i * 4 + 1],
+            matrix[i * 4 + 2],
+            matrix[i * 4 + 3],
+        ];
+        i32::from_ne_bytes(bytes) as f64
+    };
+    // ffmpeg's av_display_rotation_get, negated as that one counts counterclockwise
+    let scale = (value(0).hypot(value(3)), value(1).hypot(value(4)));
+    if scale.0 == 0.0 || scale.1 == 0.0 {
+        return 0;
+    }
+    let degrees = (value(1) / scale.1).atan2(value(0) / scale.0).to_degrees();
+    (degrees.round() as i32).rem_euclid(360)
+}
+
+/// The longest gap between keyframes
+///
+/// The time after the last keyframe counts too, so a file with a single keyframe reports how
+/// long it went without another.
+///
+/// # Parameters
+/// * `keyframes` - Keyframe times in seconds, in order
+/// * `end` - The time of the last packet read
+///
+/// # Returns
+/// * `Option<f64>` - The gap in seconds, `None` without keyframes
+pub fn keyframe_interval(keyframes: &[f64], end: f64) -> Option<f64> {
+    let last = *keyframes.last()?;
+    let gaps = keyframes.windows(2).map(|pair| pair[1] - pair[0]);
+    let interval = gaps.chain([end - last]).fold(0.0, f64::max);
+    (interval > 0.0).then(|| interval)
+}
@@ -0,0 +1,594 @@
+use crate::data::generate::format_timestamp;
+use crate::errors;
+use crate::live::options::MAX_WIDTH;
+use ffmpeg_next::software::scaling;
+use ffmpeg_next::{codec, decoder, encoder, format, frame, media, Packet, Rational};
+use std::path::{Path, PathBuf};
+
+/// Frames further ahead than this are reached by seeking rather than decoding, in seconds
+const SEEK_AHEAD: f64 = 5.0;
+/// JPEG quantizer range, lower is better, like ffmpeg's `-q:v`
+const JPEG_QUALITY: std::ops::RangeInclusive<u32> = 2..=31;
+const STORYBOARD_VTT: &str = "storyboard.vtt";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Png => "png",
+        }
+    }
+}
+
+/// Size and encoding of extracted images
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThumbnailOptions {
+    pub width: u32,
+    /// Image height, following the aspect ratio when `None`
+    pub height: Option<u32>,
+    pub format: ImageFormat,
+    /// JPEG quality from 2, the best, to 31
+    pub quality: u32,
+}
+
+impl Default for ThumbnailOptions {
+    fn default() -> Self {
+        ThumbnailOptions {
+            width: 320,
+            height: None,
+            format: ImageFormat::Jpeg,
+            quality: 4,
+        }
+    }
+}
+
+impl ThumbnailOptions {
+    pub fn with_size(mut self, width: u32, height: Option<u32>) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn with_format(mut self, format: ImageFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Check the options can be used
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result or a `THUMBNAILOPTIONS` error describing the problem
+    pub fn validate(&self) -> Result<(), errors::Error> {
+        let invalid = |reason: String| Err(errors::Error::THUMBNAILOPTIONS(reason));
+
+        if self.width == 0 || self.width > MAX_WIDTH {
+            return invalid(format!(
+                "width must be between 1 and {}, got {}",
+                MAX_WIDTH, self.width
+            ));
+        }
+        if self.height == Some(0) {
+            return invalid(String::from("height must not be zero"));
+        }
+        if self.format == ImageFormat::Jpeg && !JPEG_QUALITY.contains(&self.quality) {
+            return invalid(format!(
+                "JPEG quality must be between {} and {}, got {}",
+                JPEG_QUALITY.start(),
+                JPEG_QUALITY.end(),
+                self.quality
+            ));
+        }
+        Ok(())
+    }
+
+    /// Image size for a source size, keeping the aspect ratio unless a height is set
+    pub fn output_size(&self, source_width: u32, source_height: u32) -> (u32, u32) {
+        match self.height {
+            Some(height) => (self.width, height),
+            None if source_width > 0 => {
+                let height = source_height as f64 * self.width as f64 / source_width as f64;
+                (self.width, (height.round() as u32).max(1))
+            }
+            None => (self.width, self.width * 9 / 16),
+        }
+    }
+}
+
+/// Layout of the sprite sheets and WebVTT storyboard for a scrubbing preview
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoryboardOptions {
+    /// Seconds between tiles
+    pub interval: f64,
+    pub columns: u32,
+    /// Rows per sprite sheet, a new sheet is started once one is full
+    pub rows: u32,
+    /// Tile size and sheet encoding
+    pub thumbnail: ThumbnailOptions,
+}
+
+impl Default for StoryboardOptions {
+    fn default() -> Self {
+        StoryboardOptions {
+            interval: 5.0,
+            columns: 10,
+            rows: 10,
+            thumbnail: ThumbnailOptions::default().with_size(160, None),
+        }
+    }
+}
+
+impl StoryboardOptions {
+    pub fn with_interval(mut self, seconds: f64) -> Self {
+        self.interval = seconds;
+        self
+    }
+
+    pub fn with_grid(mut self, columns: u32, rows: u32) -> Self {
+        self.columns = columns;
+        self.rows = rows;
+        self
+    }
+
+    /// Check the options can be used
+    ///
+    /// # Returns
+    /// * `Result<(), errors::Error>` - An empty result or a `THUMBNAILOPTIONS` error describing the problem
+    pub fn validate(&self) -> Result<(), errors::Error> {
+        if !(self.interval > 0.0) {
+            return Err(errors::Error::THUMBNAILOPTIONS(format!(
+                "interval must be above 0, got {}",
+                self.interval
+            )));
+        }
+        if self.columns == 0 || self.rows == 0 {
+            return Err(errors::Error::THUMBNAILOPTIONS(String::from(
+                "a sprite sheet needs at least one column and row",
+            )));
+        }
+        self.thumbnail.validate()
+    }
+}
+
+/// An image written by `thumbnails`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Thumbnail {
+    /// Seconds from the start of the file
+    pub timestamp: f64,
+    pub path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// One tile of a storyboard, covering `start` to `end` seconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoryboardTile {
+    pub start: f64,
+    pub end: f64,
+    /// Index of the sprite sheet the tile is on
+    pub sheet: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The files written by `storyboard`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Storyboard {
+    pub sheets: Vec<PathBuf>,
+    pub vtt: PathBuf,
+    pub tiles: Vec<StoryboardTile>,
+}
+
+/// Times every `interval` seconds from the start, up to `duration`
+pub fn interval_timestamps(duration: f64, interval: f64) -> Vec<f64> {
+    if !(interval > 0.0) {
+        return vec![];
+    }
+    (0..)
+        .map(|i| i as f64 * interval)
+        .take_while(|timestamp| *timestamp < duration)
+        .collect()
+}
+
+/// Lay out one tile every `interval` seconds over the sprite sheets
+///
+/// # Parameters
+/// * `duration` - The file duration in seconds
+/// * `options` - Interval and grid
+/// * `width` - Tile width
+/// * `height` - Tile height
+///
+/// # Returns
+/// * `Vec<StoryboardTile>` - The tiles in time order, filling each sheet row by row
+pub fn storyboard_tiles(
+    duration: f64,
+    options: &StoryboardOptions,
+    width: u32,
+    height: u32,
+) -> Vec<StoryboardTile> {
+    let per_sheet = (options.columns * options.rows).max(1) as usize;
+    let columns = options.columns.max(1) as usize;
+    interval_timestamps(duration, options.interval)
+        .into_iter()
+        .enumerate()
+        .map(|(i, start)| {
+            let position = i % per_sheet;
+            StoryboardTile {
+                start,
+                end: (start + options.interval).min(duration),
+                sheet: i / per_sheet,
+                x: (position % columns) as u32 * width,
+                y: (position / columns) as u32 * height,
+                width,
+                height,
+            }
+        })
+        .collect()
+}
+
+/// The file name of a sprite sheet
+pub fn sheet_name(sheet: usize, format: ImageFormat) -> String {
+    format!("storyboard_{}.{}", sheet, format.extension())
+}
+
+/// Write a WebVTT storyboard, pointing each cue at its tile with a `#xywh=` fragment
+///
+/// # Parameters
+/// * `tiles` - The tiles from `storyboard_tiles`
+/// * `format` - The sprite sheet format, for the file names
+///
+/// # Returns
+/// * `String` - The WebVTT file, with sheet paths relative to it
+pub fn storyboard_vtt(tiles: &[StoryboardTile], format: ImageFormat) -> String {
+    let mut vtt = String::from("WEBVTT\n");
+    for tile in tiles {
+        vtt.push_str(&format!(
+            "\n{} --> {}\n{}#xywh={},{},{},{}\n",
+            format_timestamp(tile.start, '.'),
+            format_timestamp(tile.end, '.'),
+            sheet_name(tile.sheet, format),
+            tile.x,
+            tile.y,
+            tile.width,
+            tile.height
+        ));
+    }
+    vtt
+}
+
+/// Decodes the frames of a file's video stream in order, seeking over long gaps
+struct FrameReader {
+    ictx: format::context::Input,
+    stream_index: usize,
+    time_base: Rational,
+    /// When the stream starts in seconds, so times count from the start of the file
+    start: f64,
+    decoder: decoder::Video,
+    frame: frame::Video,
+    /// The time of `frame`, `None` before the first frame and after a seek
+    timestamp: Option<f64>,
+    flushed: bool,
+}
+
+impl FrameReader {
+    fn open(file_path: &str) -> Result<Self, errors::Error> {
+        ffmpeg_next::init()?;
+        let ictx = format::input(file_path)?;
+        let (stream_index, time_base, start, decoder) = {
+            let ist = ictx
+                .streams()
+                .best(media::Type::Video)
+                .ok_or(ffmpeg_next::Error::StreamNotFound)?;
+            let decoder = codec::context::Context::from_parameters(ist.parameters())?
+                .decoder()
+                .video()?;
+            let start = match ist.start_time() {
+                ffmpeg_next::ffi::AV_NOPTS_VALUE => 0.0,
+                start => start as f64 * f64::from(ist.time_base()),
+            };
+            (ist.index(), ist.time_base(), start, decoder)
+        };
+        Ok(FrameReader {
+            ictx,
+            stream_index,
+            time_base,
+            start,
+            decoder,
+            frame: frame::Video::empty(),
+            timestamp: None,
+            flushed: false,
+        })
+    }
+
+    /// The file duration in seconds, 0 when unknown
+    fn duration(&self) -> f64 {
+        self.ictx.duration().max(0) as f64 / ffmpeg_next::ffi::AV_TIME_BASE as f64
+    }
+
+    fn size(&self) -> (u32, u32) {
+        (self.decoder.width(), self.decoder.height())
+    }
+
+    /// Decode the next frame, returning false at the end of the stream
+    fn next_frame(&mut self) -> Result<bool, errors::Error> {
+        loop {
+            // Receiving clears the frame it is given, so keep the last one until there is another
+            let mut frame = frame::Video::empty();
+            if self.decoder.receive_frame(&mut frame).is_ok() {
+                let pts = frame.timestamp().unwrap_or(0);
+                self.timestamp = Some(pts as f64 * f64::from(self.time_base) - self.start);
+                self.frame = frame;
+                return Ok(true);
+            }
+            if self.flushed {
+                return Ok(false);
+            }
+            match self.ictx.packets().next() {
+                Some((stream, packet)) => {
+                    if stream.index() == self.stream_index {
+                        self.decoder.send_packet(&packet)?;
+                    }
+                }
+                None => {
+                    self.decoder.send_eof()?;
+                    self.flushed = true;
+                }
+            }
+        }
+    }
+
+    /// The first frame at or after `timestamp` seconds, or the last frame when it is past the end
+    ///
+    /// Timestamps must not go backwards between calls.
+    fn frame_at(&mut self, timestamp: f64) -> Result<Option<&frame::Video>, errors::Error> {
+        let behind = timestamp - self.timestamp.unwrap_or(0.0);
+        if behind > SEEK_AHEAD {
+            // Lands on the keyframe before the position, decoding goes on from there
+            let position =
+                ((timestamp + self.start) * ffmpeg_next::ffi::AV_TIME_BASE as f64) as i64;
+            self.ictx.seek(position, ..position)?;
+            self.decoder.flush();
+            self.timestamp = None;
+            self.flushed = false;
+        }
+        while self.timestamp.map_or(true, |current| current < timestamp) {
+            if !self.next_frame()? {
+                break;
+            }
+        }
+        Ok(self.timestamp.map(|_| &self.frame))
+    }
+}
+
+/// Scale a frame to an RGB image
+fn scale(
+    frame: &frame::Video,
+    width: u32,
+    height: u32,
+) -> Result<frame::Video, ffmpeg_next::Error> {
+    let mut scaler = scaling::Context::get(
+        frame.format(),
+        frame.width(),
+        frame.height(),
+        format::Pixel::RGB24,
+        width,
+        height,
+        scaling::Flags::BILINEAR,
+    )?;
+    let mut scaled = frame::Video::empty();
+    scaler.run(frame, &mut scaled)?;
+    Ok(scaled)
+}
+
+/// Copy an RGB image into a larger one
+fn blit(image: &frame::Video, sheet: &mut frame::Video, x: u32, y: u32) {
+    let row_bytes = image.width() as usize * 3;
+    let (stride, sheet_stride) = (image.stride(0), sheet.stride(0));
+    let sheet_data = sheet.data_mut(0);
+    for (row, line) in image
+        .data(0)
+        .chunks(stride)
+        .take(image.height() as usize)
+        .enumerate()
+    {
+        let offset = (y as usize + row) * sheet_stride + x as usize * 3;
+        sheet_data[offset..offset + row_bytes].copy_from_slice(&line[..row_bytes]);
+    }
+}
+
+/// Encode an RGB image into a JPEG or PNG file
+fn write_image(
+    image: &frame::Video,
+    path: &Path,
+    options: &ThumbnailOptions,
+) -> Result<(), errors::Error> {
+    let (id, pixel_format) = match options.format {
+        ImageFormat::Jpeg => (codec::Id::MJPEG, format::Pixel::YUVJ420P),
+        ImageFormat::Png => (codec::Id::PNG, format::Pixel::RGB24),
+    };
+    let codec = encoder::find(id).ok_or(ffmpeg_next::Error::EncoderNotFound)?;
+    let mut encoder = codec::context::Context::new().encoder().video()?;
+    encoder.set_width(image.width());
+    encoder.set_height(image.height());
+    encoder.set_format(pixel_format);
+    encoder.set_time_base(Rational(1, 1));
+    if options.format == ImageFormat::Jpeg {
+        encoder.set_flags(codec::Flags::QSCALE);
+        encoder.set_global_quality(options.quality as i32 * ffmpeg_next::ffi::FF_QP2LAMBDA as i32);
+    }
+    let mut encoder = encoder.open_as(codec)?;
+
+    let mut converted = frame::Video::empty();
+    let frame = if pixel_format == format::Pixel::RGB24 {
+        image
+    } else {
+        scaling::Context::get(
+            format::Pixel::RGB24,
+            image.width(),
+            image.height(),
+            pixel_format,
+            image.width(),
+            image.height(),
+            scaling::Flags::BILINEAR,
+        )?
+        .run(image, &mut converted)?;
+        &converted
+    };
+    encoder.send_frame(frame)?;
+    encoder.send_eof()?;
+
+    let mut data = Vec::new();
+    let mut packet = Packet::empty();
+    while encoder.receive_packet(&mut packet).is_ok() {
+        data.extend_from_slice(packet.data().unwrap_or_default());
+    }
+    std::fs::write(path, data).map_err(|_| errors::Error::IO)
+}
+
+fn write_thumbnails(
+    reader: &mut FrameReader,
+    timestamps: &[f64],
+    dir: &Path,
+    options: &ThumbnailOptions,
+) -> Result<Vec<Thumbnail>, errors::Error> {
+    if timestamps.iter().any(|timestamp| !(*timestamp >= 0.0)) {
+        return Err(errors::Error::THUMBNAILOPTIONS(String::from(
+            "timestamps must not be negative",
+        )));
+    }
+    std::fs::create_dir_all(dir).map_err(|_| errors::Error::IO)?;
+    let mut timestamps = timestamps.to_vec();
+    timestamps.sort_by(f64::total_cmp);
+
+    let (source_width, source_height) = reader.size();
+    let (width, height) = options.output_size(source_width, source_height);
+    let mut thumbnails = Vec::new();
+    for (i, timestamp) in timestamps.into_iter().enumerate() {
+        let frame = reader
+            .frame_at(timestamp)?
+            .ok_or(errors::Error::FFMPEG(String::from(
+                "the video stream has no frames",
+            )))?;
+        let path = dir.join(format!("thumbnail_{}.{}", i, options.format.extension()));
+        write_image(&scale(frame, width, height)?, &path, options)?;
+        thumbnails.push(Thumbnail {
+            timestamp,
+            path,
+            width,
+            height,
+        });
+    }
+    Ok(thumbnails)
+}
+
+/// Extract poster frames at given times
+///
+/// Each image is the first frame at or after its time, or the last frame for times past the end.
+///
+/// # Parameters
+/// * `file_path` - The video file
+/// * `timestamps` - Seconds from the start of the file
+/// * `dir` - Where the images are written, as `thumbnail_<n>.jpg`, created if missing
+/// * `options` - Image size and format, validated before anything is decoded
+///
+/// # Returns
+/// * `Result<Vec<Thumbnail>, errors::Error>` - The images in time order, or an error
+pub fn thumbnails(
+    file_path: &str,
+    timestamps: &[f64],
+    dir: &Path,
+    options: &ThumbnailOptions,
+) -> Result<Vec<Thumbnail>, errors::Error> {
+    options.validate()?;
+    let mut reader = FrameReader::open(file_path)?;
+    write_thumbnails(&mut reader, timestamps, dir, options)
+}
+
+/// Extract a frame every `interval` seconds, starting with the first
+///
+/// # Parameters
+/// * `file_path` - The video file
+/// * `interval` - Seconds between images
+/// * `dir` - Where the images are written, as `thumbnail_<n>.jpg`, created if missing
+/// * `options` - Image size and format, validated before anything is decoded
+///
+/// # Returns
+/// * `Result<Vec<Thumbnail>, errors::Error>` - The images in time order, or an error
+pub fn thumbnails_every(
+    file_path: &str,
+    interval: f64,
+    dir: &Path,
+    options: &ThumbnailOptions,
+) -> Result<Vec<Thumbnail>, errors::Error> {
+    options.validate()?;
+    if !(interval > 0.0) {
+        return Err(errors::Error::THUMBNAILOPTIONS(format!(
+            "interval must be above 0, got {}",
+            interval
+        )));
+    }
+    let mut reader = FrameReader::open(file_path)?;
+    // A file without a duration still gets its first frame
+    let timestamps = interval_timestamps(reader.duration().max(interval), interval);
+    write_thumbnails(&mut reader, &timestamps, dir, options)
+}
+
+/// Build sprite sheets of tiles every `interval` seconds and the WebVTT storyboard pointing at them
+///
+/// Players show the tile under the cursor while scrubbing. The sheets are written as
+/// `storyboard_<n>.jpg` next to `storyboard.vtt`.
+///
+/// # Parameters
+/// * `file_path` - The video file
+/// * `dir` - Where the files are written, created if missing
+/// * `options` - Interval, grid and tile size, validated before anything is decoded
+///
+/// # Returns
+/// * `Result<Storyboard, errors::Error>` - The written files and tile layout, or an error
+pub fn storyboard(
+    file_path: &str,
+    dir: &Path,
+    options: &StoryboardOptions,
+) -> Result<Storyboard, errors::Error> {
+    options.validate()?;
+    let mut reader = FrameReader::open(file_path)?;
+    let duration = reader.duration();
+    if duration <= 0.0 {
+        return Err(errors::Error::FFMPEG(String::from(
+            "the file has no duration to lay a storyboard over",
+        )));
+    }
+    let (source_width, source_height) = reader.size();
+    let (width, height) = options.thumbnail.output_size(source_width, source_height);
+    let tiles = storyboard_tiles(duration, options, width, height);
+    std::fs::create_dir_all(dir).map_err(|_| errors::Error::IO)?;
+
+    let per_sheet = (options.columns * options.rows) as usize;
+    let mut sheets = Vec::new();
+    for (index, sheet_tiles) in tiles.chunks(per_sheet).enumerate() {
+        let columns = sheet_tiles.len().min(options.columns as usize) as u32;
+        let rows = ((sheet_tiles.len() as u32) + options.columns - 1) / options.columns;
+        let mut sheet = frame::Video::new(format::Pixel::RGB24, columns * width, rows * height);
+        sheet.data_mut(0).fill(0);
+        for tile in sheet_tiles {
+            if let Some(frame) = reader.frame_at(tile.start)? {
+                blit(&scale(frame, width, height)?, &mut sheet, tile.x, tile.y);
+            }
+        }
+        let path = dir.join(sheet_name(index, options.thumbnail.format));
+        write_image(&sheet, &path, &options.thumbnail)?;
+        sheets.push(path);
+    }
+
+    let vtt = dir.join(STORYBOARD_VTT);
+    std::fs::write(&vtt, storyboard_vtt(&tiles, options.thumbnail.format))
+        .map_err(|_| errors::Error::IO)?;
+    Ok(Storyboard { sheets, vtt, tiles })
+}
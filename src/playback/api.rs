@@ -15,9 +15,13 @@ impl crate::playback::Playback for PlaybackApi {
     /// * `id` - The ID of the playback
     ///
     /// # Returns
-    /// * `Result<serde_json::Value, errors::Error>` - A JSON value containing the playback information or an error
-    fn get_playback_info(&self, id: &String) -> Result<serde_json::Value, errors::Error> {
-        self._get_playback_info(id)
+    /// * `Result<crate::data::playback::PlaybackInfo, errors::Error>` - The playback information or an error
+    fn get_playback_info(
+        &self,
+        id: &String,
+    ) -> Result<crate::data::playback::PlaybackInfo, errors::Error> {
+        let info = self._get_playback_info(id)?;
+        serde_json::from_value(info).map_err(|_| errors::Error::PLAYBACKINFO)
     }
 }
 
@@ -1,5 +1,8 @@
 pub mod api;
 
 pub trait Playback {
-    fn get_playback_info(&self, id: &String) -> Result<serde_json::Value, crate::errors::Error>;
+    fn get_playback_info(
+        &self,
+        id: &String,
+    ) -> Result<crate::data::playback::PlaybackInfo, crate::errors::Error>;
 }
@@ -11,6 +11,7 @@ impl SurfRequest {
         url: String,
         body: Option<String>,
         client: crate::LivepeerClient,
+        timeout: Option<std::time::Duration>,
     ) -> Result<serde_json::Value, errors::Error> {
         let mut req = surf::Request::builder(method, url.parse().unwrap())
             .header("Authorization", format!("Bearer {}", client.config.api_token));
@@ -19,38 +20,49 @@ impl SurfRequest {
             req = req.header("Content-Type", "application/json").body(body);
         }
 
-        let mut res: Result<serde_json::Value, errors::Error> = Err(errors::Error::UNKNOWN);
+        Self::send(req, timeout).await
+    }
 
-        let response = req.await;
+    async fn send(
+        req: surf::RequestBuilder,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<serde_json::Value, errors::Error> {
+        let response = Self::dispatch(req, timeout).await?;
 
         match response {
             Ok(mut response) => match response.status() {
-                StatusCode::Ok | StatusCode::Created | StatusCode::NoContent => {
-                    if response.status() == StatusCode::NoContent {
-                        res = Ok(serde_json::Value::Null);
-                    } else {
-                        let body = response.body_json::<serde_json::Value>().await.unwrap();
-                        res = Ok(body);
-                    }
-                }
-                _ => {
-                    let err = errors::Error::from_response(&response);
-                    res = Err(err);
-                }
+                StatusCode::NoContent => Ok(serde_json::Value::Null),
+                StatusCode::Ok | StatusCode::Created => response
+                    .body_json::<serde_json::Value>()
+                    .await
+                    .map_err(|e| errors::Error::REQUEST(e.to_string())),
+                _ => Err(errors::Error::from_response(&response)),
             },
-            Err(e) => {
-                println!("{:?}", e);
-            }
+            Err(e) => Err(errors::Error::REQUEST(e.to_string())),
         }
+    }
 
-        res
+    async fn dispatch(
+        req: surf::RequestBuilder,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<surf::Result<surf::Response>, errors::Error> {
+        Ok(match timeout {
+            Some(timeout) => {
+                let http: surf::Client = surf::Config::new()
+                    .set_timeout(Some(timeout))
+                    .try_into()
+                    .map_err(|_| errors::Error::UNKNOWN)?;
+                http.send(req.build()).await
+            }
+            None => req.await,
+        })
     }
 
     pub fn get(
         url: String,
         client: crate::LivepeerClient,
     ) -> Result<serde_json::Value, errors::Error> {
-        async_std::task::block_on(Self::make_request(surf::http::Method::Get, url, None, client))
+        async_std::task::block_on(Self::make_request(surf::http::Method::Get, url, None, client, None))
     }
 
     pub fn post(
@@ -58,7 +70,17 @@ impl SurfRequest {
         body: String,
         client: crate::LivepeerClient,
     ) -> Result<serde_json::Value, errors::Error> {
-        async_std::task::block_on(Self::make_request(surf::http::Method::Post, url, Some(body), client))
+        async_std::task::block_on(Self::make_request(surf::http::Method::Post, url, Some(body), client, None))
+    }
+
+    /// Post a JSON body with a custom timeout, for endpoints slower than the default 60s
+    pub fn post_with_timeout(
+        url: String,
+        body: String,
+        client: crate::LivepeerClient,
+        timeout: std::time::Duration,
+    ) -> Result<serde_json::Value, errors::Error> {
+        async_std::task::block_on(Self::make_request(surf::http::Method::Post, url, Some(body), client, Some(timeout)))
     }
 
     pub fn patch(
@@ -66,13 +88,271 @@ impl SurfRequest {
         body: String,
         client: crate::LivepeerClient,
     ) -> Result<serde_json::Value, errors::Error> {
-        async_std::task::block_on(Self::make_request(surf::http::Method::Patch, url, Some(body), client))
+        async_std::task::block_on(Self::make_request(surf::http::Method::Patch, url, Some(body), client, None))
     }
 
     pub fn delete(
         url: String,
         client: crate::LivepeerClient,
     ) -> Result<serde_json::Value, errors::Error> {
-        async_std::task::block_on(Self::make_request(surf::http::Method::Delete, url, None, client))
+        async_std::task::block_on(Self::make_request(surf::http::Method::Delete, url, None, client, None))
+    }
+
+    /// Post a multipart form, used by the AI generate pipelines
+    ///
+    /// # Parameters
+    /// * `url` - The URL to post to
+    /// * `form` - The form to send
+    /// * `client` - The Livepeer client
+    /// * `timeout` - Request timeout, generation can take far longer than the default 60s
+    pub fn post_multipart(
+        url: String,
+        form: Multipart,
+        client: crate::LivepeerClient,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<serde_json::Value, errors::Error> {
+        let req = surf::Request::builder(surf::http::Method::Post, url.parse().unwrap())
+            .header("Authorization", format!("Bearer {}", client.config.api_token))
+            .header("Content-Type", form.content_type())
+            .body(form.finish());
+        async_std::task::block_on(Self::send(req, timeout))
+    }
+
+    /// Post a JSON body and return the response unread, for server-sent events
+    ///
+    /// # Parameters
+    /// * `url` - The URL to post to
+    /// * `body` - The JSON body
+    /// * `client` - The Livepeer client
+    /// * `timeout` - Timeout for the response headers; reading the body is not covered
+    pub fn post_stream(
+        url: String,
+        body: String,
+        client: crate::LivepeerClient,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<surf::Response, errors::Error> {
+        let req = surf::Request::builder(surf::http::Method::Post, url.parse().unwrap())
+            .header("Authorization", format!("Bearer {}", client.config.api_token))
+            .header("Content-Type", "application/json")
+            .header("Accept", "text/event-stream")
+            .body(body);
+
+        match async_std::task::block_on(Self::dispatch(req, timeout))? {
+            Ok(response) => match response.status() {
+                StatusCode::Ok => Ok(response),
+                _ => Err(errors::Error::from_response(&response)),
+            },
+            Err(e) => Err(errors::Error::REQUEST(e.to_string())),
+        }
+    }
+
+    /// Follow a redirect without sending anything, to find the node that will take a request
+    ///
+    /// # Parameters
+    /// * `url` - The URL to resolve
+    /// * `timeout` - Request timeout
+    ///
+    /// # Returns
+    /// * `Result<String, errors::Error>` - The redirect target, `url` when it doesn't redirect, or an error
+    pub fn resolve_redirect(
+        url: String,
+        timeout: std::time::Duration,
+    ) -> Result<String, errors::Error> {
+        async_std::task::block_on(async {
+            match Self::dispatch(surf::head(&url), Some(timeout)).await? {
+                Ok(response) if response.status().is_redirection() => response
+                    .header("Location")
+                    .and_then(|location| surf::Url::parse(&url).ok()?.join(location.as_str()).ok())
+                    .map(|location| location.to_string())
+                    .ok_or(errors::Error::REQUEST(String::from(
+                        "redirect without a valid Location",
+                    ))),
+                Ok(_) => Ok(url),
+                Err(e) => Err(errors::Error::REQUEST(e.to_string())),
+            }
+        })
+    }
+
+    /// Post an SDP offer and return the answer, for WebRTC signalling such as WHIP
+    ///
+    /// # Parameters
+    /// * `url` - The URL to post to
+    /// * `offer` - The SDP offer
+    /// * `timeout` - Request timeout
+    ///
+    /// # Returns
+    /// * `Result<(String, Option<String>), errors::Error>` - The SDP answer and the absolute URL of the `Location` header, or an error
+    pub fn post_sdp(
+        url: String,
+        offer: String,
+        timeout: std::time::Duration,
+    ) -> Result<(String, Option<String>), errors::Error> {
+        let req = surf::Request::builder(surf::http::Method::Post, url.parse().unwrap())
+            .header("Content-Type", "application/sdp")
+            .body(offer);
+
+        async_std::task::block_on(async {
+            match Self::dispatch(req, Some(timeout)).await? {
+                Ok(mut response) => match response.status() {
+                    StatusCode::Ok | StatusCode::Created => {
+                        let location = response
+                            .header("Location")
+                            .and_then(|location| surf::Url::parse(&url).ok()?.join(location.as_str()).ok())
+                            .map(|location| location.to_string());
+                        let answer = response
+                            .body_string()
+                            .await
+                            .map_err(|e| errors::Error::REQUEST(e.to_string()))?;
+                        Ok((answer, location))
+                    }
+                    _ => Err(errors::Error::from_response(&response)),
+                },
+                Err(e) => Err(errors::Error::REQUEST(e.to_string())),
+            }
+        })
+    }
+
+    /// Send a binary body and return the raw response, for media ingest such as HTTP segment push
+    ///
+    /// # Parameters
+    /// * `method` - The HTTP method
+    /// * `url` - The URL to send to
+    /// * `body` - The body
+    /// * `headers` - Request headers, including the `Content-Type`
+    /// * `timeout` - Request timeout
+    ///
+    /// # Returns
+    /// * `Result<(Option<String>, Vec<u8>), errors::Error>` - The response content type and body, or an error for a non-2xx status
+    pub fn send_bytes(
+        method: surf::http::Method,
+        url: String,
+        body: Vec<u8>,
+        headers: &[(&str, String)],
+        timeout: std::time::Duration,
+    ) -> Result<(Option<String>, Vec<u8>), errors::Error> {
+        let mut req = surf::Request::builder(method, url.parse().unwrap()).body(body);
+        for (name, value) in headers {
+            req = req.header(*name, value.as_str());
+        }
+
+        async_std::task::block_on(async {
+            match Self::dispatch(req, Some(timeout)).await? {
+                Ok(mut response) if response.status().is_success() => {
+                    let content_type = response.header("Content-Type").map(|value| value.as_str().to_string());
+                    let body = response
+                        .body_bytes()
+                        .await
+                        .map_err(|e| errors::Error::REQUEST(e.to_string()))?;
+                    Ok((content_type, body))
+                }
+                Ok(response) => Err(errors::Error::from_response(&response)),
+                Err(e) => Err(errors::Error::REQUEST(e.to_string())),
+            }
+        })
+    }
+
+    /// Delete a resource that answers with no JSON body, such as a WHIP session
+    pub fn delete_resource(url: String) -> Result<(), errors::Error> {
+        async_std::task::block_on(async {
+            match surf::delete(url).await {
+                Ok(response) if response.status().is_success() => Ok(()),
+                Ok(response) => Err(errors::Error::from_response(&response)),
+                Err(e) => Err(errors::Error::REQUEST(e.to_string())),
+            }
+        })
+    }
+
+    /// Download a file, without sending the API token
+    ///
+    /// # Parameters
+    /// * `url` - The file to download
+    ///
+    /// # Returns
+    /// * `Result<(Option<String>, Vec<u8>), errors::Error>` - The response content type and body, or an error
+    pub fn download(url: String) -> Result<(Option<String>, Vec<u8>), errors::Error> {
+        async_std::task::block_on(async {
+            match surf::get(url).await {
+                Ok(mut response) => match response.status() {
+                    StatusCode::Ok => {
+                        let content_type = response
+                            .header("Content-Type")
+                            .map(|value| value.as_str().to_string());
+                        let body = response
+                            .body_bytes()
+                            .await
+                            .map_err(|e| errors::Error::REQUEST(e.to_string()))?;
+                        Ok((content_type, body))
+                    }
+                    _ => Err(errors::Error::from_response(&response)),
+                },
+                Err(e) => Err(errors::Error::REQUEST(e.to_string())),
+            }
+        })
+    }
+}
+
+/// `multipart/form-data` body builder
+#[derive(Debug, Clone)]
+pub struct Multipart {
+    boundary: String,
+    body: Vec<u8>,
+}
+
+impl Multipart {
+    pub fn new() -> Self {
+        Multipart {
+            boundary: format!("livepeer-rs-{:016x}", rand::random::<u64>()),
+            body: Vec::new(),
+        }
+    }
+
+    /// Add a text field
+    pub fn text(mut self, name: &str, value: &str) -> Self {
+        self.body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                self.boundary, name, value
+            )
+            .as_bytes(),
+        );
+        self
+    }
+
+    /// Add a file field
+    pub fn file(mut self, name: &str, file_name: &str, content_type: &str, data: &[u8]) -> Self {
+        self.body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+                self.boundary, name, file_name, content_type
+            )
+            .as_bytes(),
+        );
+        self.body.extend_from_slice(data);
+        self.body.extend_from_slice(b"\r\n");
+        self
+    }
+
+    /// Add every set field of a serializable struct as a text field
+    pub fn fields<T: serde::Serialize>(mut self, params: &T) -> Self {
+        if let Ok(serde_json::Value::Object(map)) = serde_json::to_value(params) {
+            for (name, value) in map {
+                match value {
+                    serde_json::Value::Null => {}
+                    serde_json::Value::String(value) => self = self.text(&name, &value),
+                    value => self = self.text(&name, &value.to_string()),
+                }
+            }
+        }
+        self
+    }
+
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        self.body
+            .extend_from_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+        self.body
     }
-}
\ No newline at end of file
+}
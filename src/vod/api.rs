@@ -38,7 +38,7 @@ impl crate::vod::Vod for VodApi {
     ///
     /// # Returns
     /// * `Result<serde_json::Value, errors::Error>` - A JSON value containing the presigned URL or an error
-    fn get_presigned_url(&self, video_name: String, playback_policy: Option<serde_json::Value>) -> Result<serde_json::Value, errors::Error> {
+    fn get_presigned_url(&self, video_name: String, playback_policy: Option<crate::data::stream::PlaybackPolicy>) -> Result<serde_json::Value, errors::Error> {
         self._get_presigned_url(video_name, playback_policy)
     }
 
@@ -109,7 +109,7 @@ impl crate::vod::Vod for VodApi {
     ///
     /// # Returns
     /// * `Result<serde_json::Value, errors::Error>` - A JSON value containing the imported asset or an error
-    fn import_asset(&self, video_file_path: String, video_name: String, playback_policy: Option<serde_json::Value>) -> Result<serde_json::Value, errors::Error> {
+    fn import_asset(&self, video_file_path: String, video_name: String, playback_policy: Option<crate::data::stream::PlaybackPolicy>) -> Result<serde_json::Value, errors::Error> {
         self._import_asset(video_file_path, video_name, playback_policy)
     }
 
@@ -207,7 +207,7 @@ impl VodApi {
     }
 
     /// Internal method to import an asset
-    fn _import_asset(&self, url: String, name: String, playback_policy: Option<serde_json::Value>) -> Result<serde_json::Value, errors::Error> {
+    fn _import_asset(&self, url: String, name: String, playback_policy: Option<crate::data::stream::PlaybackPolicy>) -> Result<serde_json::Value, errors::Error> {
         let body = if let Some(policy) = playback_policy {
             serde_json::json!({ "url": url, "name": name, "playbackPolicy": policy }).to_string()
         } else {
@@ -221,8 +221,12 @@ impl VodApi {
     }
 
     /// Internal method to get a presigned URL for uploading a video
-    fn _get_presigned_url(&self, video_name: String, playback_policy: Option<serde_json::Value>) -> Result<serde_json::Value, errors::Error> {
-        let body = serde_json::to_string(&serde_json::json!({ "name": video_name, "playbackPolicy": playback_policy })).unwrap();
+    fn _get_presigned_url(&self, video_name: String, playback_policy: Option<crate::data::stream::PlaybackPolicy>) -> Result<serde_json::Value, errors::Error> {
+        let body = if let Some(policy) = playback_policy {
+            serde_json::json!({ "name": video_name, "playbackPolicy": policy }).to_string()
+        } else {
+            serde_json::json!({ "name": video_name }).to_string()
+        };
         crate::utils::SurfRequest::post(
             format!("{}{}", self.client.config.host, self.urls.vod.get_presigned_url),
             body,
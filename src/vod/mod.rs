@@ -12,7 +12,7 @@ pub trait Vod {
     fn get_presigned_url(
         &self,
         video_name: String,
-        playback_policy: Option<serde_json::Value>,
+        playback_policy: Option<crate::data::stream::PlaybackPolicy>,
     ) -> Result<serde_json::Value, crate::errors::Error>;
     fn upload_asset(
         &self,
@@ -43,7 +43,7 @@ pub trait Vod {
         &self,
         url: String,
         name: String,
-        playback_policy: Option<serde_json::Value>,
+        playback_policy: Option<crate::data::stream::PlaybackPolicy>,
     ) -> Result<serde_json::Value, crate::errors::Error>;
     fn export_to_ipfs(
         &self,